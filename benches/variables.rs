@@ -0,0 +1,32 @@
+/*- Imports -*/
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use wasm_terminal::Terminal;
+
+/*- Looking up a variable and calling a function should
+    take about the same time no matter how many there are -*/
+fn lookups(c:&mut Criterion) {
+    let mut group = c.benchmark_group("lookups");
+
+    for count in [10, 100, 1000] {
+        let mut term = Terminal::new();
+        for i in 0..count {
+            term.run(format!("set var{i} {i}"));
+            term.run(format!("fn func{i}() return {i}"));
+        };
+
+        group.bench_with_input(BenchmarkId::new("variable", count), &count, |b, _| {
+            b.iter(|| term.run(String::from("return <var5>")))
+        });
+        group.bench_with_input(BenchmarkId::new("function", count), &count, |b, _| {
+            b.iter(|| term.run(String::from("exec func5()")))
+        });
+        group.bench_with_input(BenchmarkId::new("repeat", count), &count, |b, _| {
+            b.iter(|| term.run(String::from("repeat 10 i return <var#i>")))
+        });
+    };
+
+    group.finish();
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
@@ -0,0 +1,953 @@
+/*- Expression parser and evaluator used by the 'calc' command -*/
+
+/*- Imports -*/
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::{collections::HashMap, fmt::Display};
+
+/*- Unary minus binds looser than powers, so -2^2 is -(2^2) -*/
+const POWER_PRECEDENCE:u8 = 7;
+
+/// How deep calculations can be nested, in parentheses or in a
+/// chain like `1 - 1 - 1`. Parsing and evaluating them is recursive
+pub const MAX_NESTING:usize = 200;
+
+/*- Name, how many arguments it takes (None for one or more),
+    what it does and a short description for 'help calc' -*/
+pub type Function = (&'static str, Option<usize>, fn(&[f64]) -> f64, &'static str);
+
+pub const FUNCTIONS:&[Function] = &[
+    ("sqrt", Some(1), |a| a[0].sqrt(), "square root"),
+    ("abs", Some(1), |a| a[0].abs(), "absolute value"),
+    ("floor", Some(1), |a| a[0].floor(), "round down"),
+    ("ceil", Some(1), |a| a[0].ceil(), "round up"),
+    ("round", Some(1), |a| a[0].round(), "round to the nearest whole number"),
+    ("sin", Some(1), |a| a[0].sin(), "sine, in radians"),
+    ("cos", Some(1), |a| a[0].cos(), "cosine, in radians"),
+    ("tan", Some(1), |a| a[0].tan(), "tangent, in radians"),
+    ("ln", Some(1), |a| a[0].ln(), "natural logarithm"),
+    ("log10", Some(1), |a| a[0].log10(), "base 10 logarithm"),
+    ("min", None, |a| a.iter().copied().fold(f64::INFINITY, f64::min), "smallest argument"),
+    ("max", None, |a| a.iter().copied().fold(f64::NEG_INFINITY, f64::max), "biggest argument"),
+    ("hypot", Some(2), |a| a[0].hypot(a[1]), "length of the hypotenuse, sqrt(x^2 + y^2)"),
+];
+
+pub const CONSTANTS:&[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("tau", std::f64::consts::TAU),
+];
+
+/// What went wrong in a calculation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    DivisionByZero,
+
+    /// A number or result that is too big
+    Overflow,
+    InvalidNumber(String),
+    UnexpectedCharacter(char),
+
+    /// A `)`, `,` or `=` where it doesn't belong
+    UnexpectedToken(&'static str),
+
+    /// A `(` that never gets closed
+    MissingParen,
+    ExpectedNumber,
+    ExpectedOperator,
+    UnknownName(String),
+    UnknownFunction(String),
+
+    /// Parentheses or operators nested deeper than [`MAX_NESTING`]
+    TooDeep,
+
+    /// Anything else, like `sqrt` in --int mode
+    Invalid(String),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            ErrorKind::Overflow => write!(f, "Number is too big"),
+            ErrorKind::InvalidNumber(number) => write!(f, "Invalid number '{number}'"),
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected '{c}'"),
+            ErrorKind::UnexpectedToken(token) => write!(f, "Unexpected '{token}'"),
+            ErrorKind::MissingParen => write!(f, "Missing ')' to close the '('"),
+            ErrorKind::ExpectedNumber => write!(f, "Expected a number"),
+            ErrorKind::ExpectedOperator => write!(f, "Expected an operator"),
+            ErrorKind::UnknownName(name) => write!(f, "Unknown name '{name}'"),
+            ErrorKind::UnknownFunction(name) => write!(f, "Unknown function '{name}'"),
+            ErrorKind::TooDeep => write!(f, "Nested too deep"),
+            ErrorKind::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// An error and the column in the calculation where it happened, from 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcError {
+    pub kind: ErrorKind,
+    pub column: usize,
+}
+
+impl Display for CalcError {
+    fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} at column {}", self.kind, self.column)
+    }
+}
+
+/*- Errors from the number types get the column of the expression -*/
+fn at<T>(result:Result<T, ErrorKind>, column:usize) -> Result<T, CalcError> {
+    result.map_err(|kind| CalcError { kind, column })
+}
+
+/*- Tokens of a calculation -*/
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Identifier(String),
+    Operator(BinaryOperator),
+    Not,
+    OpenParen,
+    CloseParen,
+    Comma,
+    Assign,
+}
+
+/// Operators that go between two numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+
+    /*- Bitwise, only for whole numbers -*/
+    BitAnd,
+    BitOr,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+/// Operators that go before a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Plus,
+    Minus,
+
+    /// Bitwise not, `~`
+    Not,
+}
+
+impl BinaryOperator {
+    /*- How hard the operator binds, and if it binds to the right -*/
+    fn precedence(&self) -> (u8, bool) {
+        match self {
+            BinaryOperator::BitOr => (1, false),
+            BinaryOperator::Xor => (2, false),
+            BinaryOperator::BitAnd => (3, false),
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => (4, false),
+            BinaryOperator::Add | BinaryOperator::Subtract => (5, false),
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => (6, false),
+
+            /*- 2^3^2 is 2^(3^2) -*/
+            BinaryOperator::Power => (POWER_PRECEDENCE, true),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Power => "^",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::Xor => "xor",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+        }
+    }
+}
+
+/// A parsed calculation, and the column it starts at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub column: usize,
+
+    /*- How deep the tree below this is -*/
+    height: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// A number, as it was written
+    Number(String),
+
+    /// A variable or a constant like `pi`
+    Identifier(String),
+
+    /// A function call like `sqrt(2)`
+    Call(String, Vec<Expr>),
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn new(kind:ExprKind, column:usize) -> Self {
+        let height = match &kind {
+            ExprKind::Number(_) | ExprKind::Identifier(_) => 0,
+            ExprKind::Call(_, arguments) => arguments.iter().map(|e| e.height + 1).max().unwrap_or(0),
+            ExprKind::Unary(_, operand) => operand.height + 1,
+            ExprKind::Binary(_, left, right) => left.height.max(right.height) + 1,
+        };
+        Self { kind, column, height }
+    }
+
+    /// Work out the value, with `f64` for normal calculations
+    /// or `BigInt` for exact integer ones. Names are looked up
+    /// in `variables` first, then in the constants
+    pub fn evaluate<N:Number>(&self, variables:&HashMap<String, String>) -> Result<N, CalcError> {
+        let column = self.column;
+        match &self.kind {
+            ExprKind::Number(number) => at(N::parse(number), column),
+            ExprKind::Identifier(name) => {
+                if let Some(value) = variables.get(name) {
+                    return at(variable(name, value), column);
+                };
+
+                match CONSTANTS.iter().find(|(constant, _)| constant == name) {
+                    Some((name, value)) => at(N::constant(name, *value), column),
+                    None => at(Err(ErrorKind::UnknownName(name.to_string())), column)
+                }
+            },
+            ExprKind::Call(name, arguments) => {
+                let function = match FUNCTIONS.iter().find(|(function, ..)| function == name) {
+                    Some(function) => function,
+                    None => return at(Err(ErrorKind::UnknownFunction(name.to_string())), column)
+                };
+
+                let (_, arity, _, _) = function;
+                let valid = match arity {
+                    Some(arity) => arguments.len() == *arity,
+                    None => !arguments.is_empty()
+                };
+                if !valid {
+                    let expected = arity.map_or(String::from("at least 1"), |e| e.to_string());
+                    let error = format!("{name}(...) takes {expected} argument(s), not {}", arguments.len());
+                    return at(Err(ErrorKind::Invalid(error)), column);
+                };
+
+                let arguments = arguments.iter().map(|e| e.evaluate(variables)).collect::<Result<Vec<N>, CalcError>>()?;
+                at(N::call(function, arguments), column)
+            },
+            ExprKind::Unary(operator, operand) => at(N::unary(*operator, operand.evaluate(variables)?), column),
+            ExprKind::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(variables)?, right.evaluate(variables)?);
+                at(N::binary(*operator, left, right), column)
+            },
+        }
+    }
+}
+
+/*- Variables are text, so they are read like number values -*/
+fn variable<N:Number>(name:&str, value:&str) -> Result<N, ErrorKind> {
+    parse_value(value).map_err(|_| ErrorKind::Invalid(format!("Variable '{name}' isn't a number: '{}'", value.trim())))
+}
+
+/// Read a number with an optional sign, like `-3` or `+0xff`.
+/// Results of 'calc --frac' look like `1/3` and are read too
+pub fn parse_value<N:Number>(value:&str) -> Result<N, String> {
+    let value = value.trim();
+    if let Some((numerator, denominator)) = value.split_once('/') {
+        let (numerator, denominator) = (parse_value(numerator)?, parse_value(denominator)?);
+        return N::binary(BinaryOperator::Divide, numerator, denominator).map_err(|e| e.to_string());
+    };
+
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.strip_prefix('+').unwrap_or(value))
+    };
+
+    match N::parse(digits) {
+        Ok(number) if negative => N::unary(UnaryOperator::Minus, number).map_err(|e| e.to_string()),
+        Ok(number) => Ok(number),
+        Err(_) => Err(format!("'{value}' isn't a number"))
+    }
+}
+
+/// Kinds of numbers that calculations can be done with
+pub trait Number: Sized + Display {
+    fn parse(text:&str) -> Result<Self, ErrorKind>;
+    fn constant(name:&str, value:f64) -> Result<Self, ErrorKind>;
+    fn call(function:&Function, arguments:Vec<Self>) -> Result<Self, ErrorKind>;
+    fn unary(operator:UnaryOperator, operand:Self) -> Result<Self, ErrorKind>;
+    fn binary(operator:BinaryOperator, left:Self, right:Self) -> Result<Self, ErrorKind>;
+}
+
+impl Number for f64 {
+    fn parse(text:&str) -> Result<Self, ErrorKind> {
+        parse_number(text)
+    }
+
+    fn constant(_:&str, value:f64) -> Result<Self, ErrorKind> {
+        Ok(value)
+    }
+
+    fn call((name, _, function, _):&Function, arguments:Vec<Self>) -> Result<Self, ErrorKind> {
+        let result = function(&arguments);
+
+        /*- sqrt(-1), ln(0) and such -*/
+        if !result.is_finite() && arguments.iter().all(|e| e.is_finite()) {
+            return Err(ErrorKind::Invalid(format!("{name}(...) is undefined for those arguments")));
+        };
+        Ok(result)
+    }
+
+    fn unary(operator:UnaryOperator, operand:Self) -> Result<Self, ErrorKind> {
+        match operator {
+            UnaryOperator::Plus => Ok(operand),
+            UnaryOperator::Minus => Ok(-operand),
+            UnaryOperator::Not => Ok(!whole(operand, "~")? as f64),
+        }
+    }
+
+    fn binary(operator:BinaryOperator, left:Self, right:Self) -> Result<Self, ErrorKind> {
+        let result = match operator {
+            BinaryOperator::Add => left + right,
+            BinaryOperator::Subtract => left - right,
+            BinaryOperator::Multiply => left * right,
+            BinaryOperator::Divide | BinaryOperator::Modulo if right == 0.0 => return Err(ErrorKind::DivisionByZero),
+            BinaryOperator::Divide => left / right,
+            BinaryOperator::Modulo => left % right,
+            BinaryOperator::Power => left.powf(right),
+            _ => {
+                let symbol = operator.symbol();
+                let (left, right) = (whole(left, symbol)?, whole(right, symbol)?);
+                let result = match operator {
+                    BinaryOperator::BitAnd => left & right,
+                    BinaryOperator::BitOr => left | right,
+                    BinaryOperator::Xor => left ^ right,
+                    _ => {
+                        let shift = match u32::try_from(right) {
+                            Ok(shift) if shift < 64 => shift,
+                            _ => return Err(ErrorKind::Invalid(format!("Can't shift by {right}")))
+                        };
+                        match operator {
+                            BinaryOperator::ShiftLeft => left << shift,
+                            _ => left >> shift,
+                        }
+                    }
+                };
+                result as f64
+            }
+        };
+
+        /*- 10^400 and such -*/
+        if result.is_infinite() && left.is_finite() && right.is_finite() {
+            return Err(ErrorKind::Overflow);
+        };
+        if result.is_nan() && !left.is_nan() && !right.is_nan() {
+            return Err(ErrorKind::Invalid(format!("{left} {} {right} isn't a real number", operator.symbol())));
+        };
+        Ok(result)
+    }
+}
+
+/*- Bitwise operators work on whole numbers only -*/
+fn whole(number:f64, symbol:&str) -> Result<i64, ErrorKind> {
+    if number.fract() != 0.0 {
+        return Err(ErrorKind::Invalid(format!("'{symbol}' needs whole numbers, not {number}")));
+    };
+    if number.abs() >= i64::MAX as f64 { return Err(ErrorKind::Overflow) };
+    Ok(number as i64)
+}
+
+/*- Biggest result a whole number may have in --int mode, so
+    that '2^99999999999' or multiplying big numbers again and
+    again doesn't freeze the terminal -*/
+const MAX_INT_BITS:u64 = 1_000_000;
+
+/*- Fractions get reduced after every step, and that gets slow
+    a lot sooner. 20000 bits is still about 6000 digits -*/
+const MAX_FRACTION_BITS:u64 = 20_000;
+
+fn limit(number:BigInt) -> Result<BigInt, ErrorKind> {
+    match number.bits() > MAX_INT_BITS {
+        true => Err(ErrorKind::Overflow),
+        false => Ok(number)
+    }
+}
+
+/*- Exact integers for 'calc --int' -*/
+impl Number for BigInt {
+    fn parse(text:&str) -> Result<Self, ErrorKind> {
+        let (radix, digits) = radix(text);
+        match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            Some(number) => Ok(number),
+            None => Err(ErrorKind::Invalid(format!("'{text}' isn't a whole number, which --int needs")))
+        }
+    }
+
+    fn constant(name:&str, _:f64) -> Result<Self, ErrorKind> {
+        Err(ErrorKind::Invalid(format!("'{name}' can't be used with --int")))
+    }
+
+    fn call((name, ..):&Function, arguments:Vec<Self>) -> Result<Self, ErrorKind> {
+        let mut arguments = arguments.into_iter();
+        match *name {
+            "abs" => Ok(arguments.next().unwrap_or_default().abs()),
+            "floor" | "ceil" | "round" => Ok(arguments.next().unwrap_or_default()),
+            "min" => Ok(arguments.min().unwrap_or_default()),
+            "max" => Ok(arguments.max().unwrap_or_default()),
+            _ => Err(ErrorKind::Invalid(format!("{name}(...) can't be used with --int")))
+        }
+    }
+
+    fn unary(operator:UnaryOperator, operand:Self) -> Result<Self, ErrorKind> {
+        match operator {
+            UnaryOperator::Plus => Ok(operand),
+            UnaryOperator::Minus => Ok(-operand),
+            UnaryOperator::Not => Ok(!operand),
+        }
+    }
+
+    fn binary(operator:BinaryOperator, left:Self, right:Self) -> Result<Self, ErrorKind> {
+        let result = match operator {
+            BinaryOperator::BitAnd => Ok(left & right),
+            BinaryOperator::BitOr => Ok(left | right),
+            BinaryOperator::Xor => Ok(left ^ right),
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                let shift = match right.to_u64() {
+                    Some(shift) => shift,
+                    None => return Err(ErrorKind::Invalid(format!("Can't shift by {right}")))
+                };
+                match operator {
+                    BinaryOperator::ShiftLeft if left.bits().saturating_add(shift) > MAX_INT_BITS => Err(ErrorKind::Overflow),
+                    BinaryOperator::ShiftLeft => Ok(left << shift),
+                    _ => Ok(left >> shift),
+                }
+            },
+            BinaryOperator::Add => Ok(left + right),
+            BinaryOperator::Subtract => Ok(left - right),
+            /*- The product has at least this many bits -*/
+            BinaryOperator::Multiply if (left.bits() + right.bits()).saturating_sub(1) > MAX_INT_BITS => Err(ErrorKind::Overflow),
+            BinaryOperator::Multiply => Ok(left * right),
+            BinaryOperator::Divide | BinaryOperator::Modulo if right.is_zero() => Err(ErrorKind::DivisionByZero),
+            BinaryOperator::Divide => {
+                if !(&left % &right).is_zero() {
+                    return Err(ErrorKind::Invalid(format!("{left} / {right} isn't a whole number")));
+                };
+                Ok(left / right)
+            },
+            BinaryOperator::Modulo => Ok(left % right),
+            BinaryOperator::Power => {
+                let exponent = match right.to_u32() {
+                    Some(exponent) => exponent,
+                    None if right.is_negative() => return Err(ErrorKind::Invalid(format!("{left} ^ {right} isn't a whole number"))),
+                    None => return Err(ErrorKind::Overflow),
+                };
+                /*- The power has at least this many bits -*/
+                if (left.bits().saturating_sub(1)).saturating_mul(exponent as u64) >= MAX_INT_BITS {
+                    return Err(ErrorKind::Overflow);
+                };
+                Ok(left.pow(exponent))
+            },
+        };
+
+        result.and_then(limit)
+    }
+}
+
+/*- The biggest whole numbers a float can hold have this many bits -*/
+const FLOAT_BITS:u64 = 1024;
+
+/// Whole numbers for plain `calc`. Exact like `--int`, but only as big
+/// as a float can go, so anything bigger overflows like it would with floats
+pub struct Whole(pub BigInt);
+
+impl Display for Whole {
+    fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+fn float_sized(number:BigInt) -> Result<Whole, ErrorKind> {
+    match number.bits() > FLOAT_BITS {
+        true => Err(ErrorKind::Overflow),
+        false => Ok(Whole(number))
+    }
+}
+
+impl Number for Whole {
+    fn parse(text:&str) -> Result<Self, ErrorKind> {
+        BigInt::parse(text).and_then(float_sized)
+    }
+
+    fn constant(name:&str, value:f64) -> Result<Self, ErrorKind> {
+        BigInt::constant(name, value).map(Whole)
+    }
+
+    fn call(function:&Function, arguments:Vec<Self>) -> Result<Self, ErrorKind> {
+        BigInt::call(function, arguments.into_iter().map(|e| e.0).collect()).and_then(float_sized)
+    }
+
+    fn unary(operator:UnaryOperator, operand:Self) -> Result<Self, ErrorKind> {
+        BigInt::unary(operator, operand.0).and_then(float_sized)
+    }
+
+    fn binary(operator:BinaryOperator, left:Self, right:Self) -> Result<Self, ErrorKind> {
+        /*- Don't work out big results that would be thrown away -*/
+        let (bits, amount) = (left.0.bits(), right.0.to_u64().unwrap_or(u64::MAX));
+        let too_big = match operator {
+            BinaryOperator::Power => bits > 1 && (bits - 1).saturating_mul(amount) >= FLOAT_BITS,
+            BinaryOperator::ShiftLeft => bits.saturating_add(amount) > FLOAT_BITS + 1,
+            BinaryOperator::Multiply => (bits + right.0.bits()).saturating_sub(1) > FLOAT_BITS,
+            _ => false
+        };
+        if too_big { return Err(ErrorKind::Overflow) };
+
+        BigInt::binary(operator, left.0, right.0).and_then(float_sized)
+    }
+}
+
+/*- Exact fractions for 'calc --frac' -*/
+impl Number for BigRational {
+    fn parse(text:&str) -> Result<Self, ErrorKind> {
+        let (radix, _) = radix(text);
+        if radix != 10 { return BigInt::parse(text).map(BigRational::from_integer) };
+
+        /*- 1.25e3 is 125 * 10^(3 - 2) -*/
+        let invalid = || ErrorKind::InvalidNumber(text.to_string());
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().map_err(|_| invalid())?),
+            None => (text, 0)
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = BigInt::parse_bytes(format!("{whole}{fraction}").as_bytes(), 10).ok_or_else(invalid)?;
+
+        /*- 10^scale has about 3.3 bits per digit -*/
+        let scale = exponent - fraction.len() as i64;
+        if scale.unsigned_abs() > MAX_FRACTION_BITS * 3 / 10 { return Err(ErrorKind::Overflow) };
+
+        let power = BigInt::from(10).pow(scale.unsigned_abs() as u32);
+        match scale >= 0 {
+            true => Ok(BigRational::from_integer(digits * power)),
+            false => Ok(BigRational::new(digits, power))
+        }
+    }
+
+    fn constant(name:&str, _:f64) -> Result<Self, ErrorKind> {
+        Err(ErrorKind::Invalid(format!("'{name}' can't be used with --frac")))
+    }
+
+    fn call((name, ..):&Function, arguments:Vec<Self>) -> Result<Self, ErrorKind> {
+        let mut arguments = arguments.into_iter();
+        let first = arguments.next().unwrap_or_default();
+        match *name {
+            "abs" => Ok(first.abs()),
+            "floor" => Ok(first.floor()),
+            "ceil" => Ok(first.ceil()),
+            "round" => Ok(first.round()),
+            "min" => Ok(arguments.fold(first, |a, b| a.min(b))),
+            "max" => Ok(arguments.fold(first, |a, b| a.max(b))),
+            _ => Err(ErrorKind::Invalid(format!("{name}(...) can't be used with --frac")))
+        }
+    }
+
+    fn unary(operator:UnaryOperator, operand:Self) -> Result<Self, ErrorKind> {
+        match operator {
+            UnaryOperator::Plus => Ok(operand),
+            UnaryOperator::Minus => Ok(-operand),
+            UnaryOperator::Not => BigInt::unary(operator, fraction_whole(operand, "~")?).map(BigRational::from_integer),
+        }
+    }
+
+    fn binary(operator:BinaryOperator, left:Self, right:Self) -> Result<Self, ErrorKind> {
+        let result = match operator {
+            BinaryOperator::Add => Ok(left + right),
+            BinaryOperator::Subtract => Ok(left - right),
+            BinaryOperator::Multiply => Ok(left * right),
+            BinaryOperator::Divide | BinaryOperator::Modulo if right.is_zero() => Err(ErrorKind::DivisionByZero),
+            BinaryOperator::Divide => Ok(left / right),
+            BinaryOperator::Modulo => Ok(left % right),
+            BinaryOperator::Power => {
+                if !right.is_integer() {
+                    return Err(ErrorKind::Invalid(format!("{left} ^ ({right}) isn't a fraction")));
+                };
+                let exponent = match right.to_integer().to_i32() {
+                    Some(exponent) => exponent,
+                    None => return Err(ErrorKind::Overflow),
+                };
+                if exponent < 0 && left.is_zero() {
+                    return Err(ErrorKind::DivisionByZero);
+                };
+                let bits = left.numer().bits().max(left.denom().bits());
+                if (bits.saturating_sub(1)).saturating_mul(exponent.unsigned_abs() as u64) >= MAX_FRACTION_BITS {
+                    return Err(ErrorKind::Overflow);
+                };
+                /*- A reduced fraction stays reduced when both parts are
+                    raised, which skips the slow gcd of every step -*/
+                let (numer, denom) = (left.numer().pow(exponent.unsigned_abs()), left.denom().pow(exponent.unsigned_abs()));
+                match exponent < 0 {
+                    true if numer.is_negative() => Ok(BigRational::new_raw(-denom, -numer)),
+                    true => Ok(BigRational::new_raw(denom, numer)),
+                    false => Ok(BigRational::new_raw(numer, denom))
+                }
+            },
+
+            /*- Bitwise operators work like they do with --int -*/
+            _ => {
+                let symbol = operator.symbol();
+                let (left, right) = (fraction_whole(left, symbol)?, fraction_whole(right, symbol)?);
+                BigInt::binary(operator, left, right).map(BigRational::from_integer)
+            }
+        };
+
+        /*- For the top and the bottom -*/
+        result.and_then(|e| match e.numer().bits().max(e.denom().bits()) > MAX_FRACTION_BITS {
+            true => Err(ErrorKind::Overflow),
+            false => Ok(e)
+        })
+    }
+}
+
+fn fraction_whole(number:BigRational, symbol:&str) -> Result<BigInt, ErrorKind> {
+    match number.is_integer() {
+        true => Ok(number.to_integer()),
+        false => Err(ErrorKind::Invalid(format!("'{symbol}' needs whole numbers, not {number}")))
+    }
+}
+
+/// Show a fraction as a decimal number
+pub fn decimal(number:&BigRational) -> String {
+    match number.to_f64() {
+        Some(number) => format(number),
+        None => number.to_string()
+    }
+}
+
+/*- Split a calculation into tokens, each with the column it starts at -*/
+fn lex(input:&str) -> Result<Vec<(Token, usize)>, CalcError> {
+    let chars:Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while let Some(&c) = chars.get(pos) {
+        let start = pos;
+        let token = match c {
+            _ if c.is_whitespace() => { pos += 1; continue; },
+            /*- 0x, 0b and 0o literals -*/
+            '0' if chars.get(pos + 1).is_some_and(|c| matches!(c, 'x' | 'X' | 'b' | 'B' | 'o' | 'O')) => {
+                pos += 2;
+                while chars.get(pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') { pos += 1; };
+
+                let number:String = chars[start..pos].iter().collect();
+                check_number(&number, start + 1)?;
+                Token::Number(number)
+            },
+            '0'..='9' | '.' => {
+                while chars.get(pos).is_some_and(|c| c.is_ascii_digit() || *c == '.') { pos += 1; };
+
+                /*- Scientific notation like 1e-5 -*/
+                if chars.get(pos).is_some_and(|c| *c == 'e' || *c == 'E') {
+                    pos += 1;
+                    if chars.get(pos).is_some_and(|c| *c == '+' || *c == '-') { pos += 1; };
+                    while chars.get(pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '.') { pos += 1; };
+                };
+
+                /*- Only checked here, the number itself is read when evaluating -*/
+                let number:String = chars[start..pos].iter().collect();
+                check_number(&number, start + 1)?;
+                Token::Number(number)
+            },
+            'a'..='z' | 'A'..='Z' | '_' => {
+                while chars.get(pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') { pos += 1; };
+
+                /*- '^' is already taken by powers -*/
+                match chars[start..pos].iter().collect::<String>() {
+                    name if name == "xor" => Token::Operator(BinaryOperator::Xor),
+                    name => Token::Identifier(name)
+                }
+            },
+            '*' if chars.get(pos + 1) == Some(&'*') => {
+                pos += 2;
+                Token::Operator(BinaryOperator::Power)
+            },
+            '<' if chars.get(pos + 1) == Some(&'<') => {
+                pos += 2;
+                Token::Operator(BinaryOperator::ShiftLeft)
+            },
+            '>' if chars.get(pos + 1) == Some(&'>') => {
+                pos += 2;
+                Token::Operator(BinaryOperator::ShiftRight)
+            },
+            _ => {
+                pos += 1;
+                match c {
+                    '+' => Token::Operator(BinaryOperator::Add),
+                    '-' => Token::Operator(BinaryOperator::Subtract),
+                    '*' => Token::Operator(BinaryOperator::Multiply),
+                    '/' => Token::Operator(BinaryOperator::Divide),
+                    '%' => Token::Operator(BinaryOperator::Modulo),
+                    '!' | '^' => Token::Operator(BinaryOperator::Power),
+                    '&' => Token::Operator(BinaryOperator::BitAnd),
+                    '|' => Token::Operator(BinaryOperator::BitOr),
+                    '~' => Token::Not,
+                    '(' => Token::OpenParen,
+                    ')' => Token::CloseParen,
+                    ',' => Token::Comma,
+                    '=' => Token::Assign,
+                    _ => return at(Err(ErrorKind::UnexpectedCharacter(c)), start + 1)
+                }
+            }
+        };
+
+        tokens.push((token, start + 1));
+    };
+
+    Ok(tokens)
+}
+
+/*- Only the way a number is written is checked when lexing. If it's
+    too big is up to the kind of number, --frac can hold 1e999 -*/
+fn check_number(number:&str, column:usize) -> Result<(), CalcError> {
+    match parse_number(number) {
+        Ok(_) | Err(ErrorKind::Overflow) => Ok(()),
+        Err(kind) => Err(CalcError { kind, column })
+    }
+}
+
+/*- Precedence climbing parser -*/
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+
+    /*- Column just after the calculation, for errors at the end -*/
+    end: usize,
+
+    /*- How many expressions the parser is inside of -*/
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    /*- Column of the next token -*/
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, column)| *column)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn error<T>(&self, kind:ErrorKind, column:usize) -> Result<T, CalcError> {
+        Err(CalcError { kind, column })
+    }
+
+    /*- Evaluating is recursive as well, so a long chain like
+        1 - 1 - 1 - ... is too deep just like lots of parentheses -*/
+    fn node(&self, kind:ExprKind, column:usize) -> Result<Expr, CalcError> {
+        let expr = Expr::new(kind, column);
+        match expr.height > MAX_NESTING {
+            true => self.error(ErrorKind::TooDeep, column),
+            false => Ok(expr)
+        }
+    }
+
+    /*- expression = operand (operator expression)*, where only
+        operators binding harder than 'min' are taken -*/
+    fn expression(&mut self, min:u8) -> Result<Expr, CalcError> {
+        /*- An error ends the parse, so the depth only has to be right on success -*/
+        if self.depth == MAX_NESTING { return self.error(ErrorKind::TooDeep, self.column()); };
+        self.depth += 1;
+        let mut left = self.operand()?;
+
+        while let Some(Token::Operator(operator)) = self.peek().cloned() {
+            let (precedence, right_associative) = operator.precedence();
+            if precedence < min { break; };
+            let column = self.column();
+            self.pos += 1;
+
+            let next_min = if right_associative { precedence } else { precedence + 1 };
+            let right = self.expression(next_min)?;
+            left = self.node(ExprKind::Binary(operator, Box::new(left), Box::new(right)), column)?;
+        };
+
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, CalcError> {
+        let column = self.column();
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::new(ExprKind::Number(number), column)),
+            Some(Token::Identifier(name)) => {
+                if self.peek() != Some(&Token::OpenParen) {
+                    return Ok(Expr::new(ExprKind::Identifier(name), column));
+                };
+                let open = self.column();
+                self.pos += 1;
+
+                /*- Comma separated arguments -*/
+                let mut arguments = Vec::new();
+                if self.peek() == Some(&Token::CloseParen) {
+                    self.pos += 1;
+                    return Ok(Expr::new(ExprKind::Call(name, arguments), column));
+                };
+                loop {
+                    arguments.push(self.expression(0)?);
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::CloseParen) => return self.node(ExprKind::Call(name, arguments), column),
+                        _ => return self.error(ErrorKind::MissingParen, open)
+                    };
+                };
+            },
+            Some(Token::OpenParen) => {
+                let inner = self.expression(0)?;
+                match self.next() {
+                    Some(Token::CloseParen) => Ok(inner),
+                    _ => self.error(ErrorKind::MissingParen, column)
+                }
+            },
+            /*- Unary operators -*/
+            Some(Token::Operator(operator @ (BinaryOperator::Add | BinaryOperator::Subtract))) => {
+                let operator = match operator {
+                    BinaryOperator::Add => UnaryOperator::Plus,
+                    _ => UnaryOperator::Minus,
+                };
+                let operand = self.expression(POWER_PRECEDENCE)?;
+                self.node(ExprKind::Unary(operator, Box::new(operand)), column)
+            },
+            Some(Token::Not) => {
+                let operand = self.expression(POWER_PRECEDENCE)?;
+                self.node(ExprKind::Unary(UnaryOperator::Not, Box::new(operand)), column)
+            },
+            Some(Token::CloseParen) => self.error(ErrorKind::UnexpectedToken(")"), column),
+            Some(Token::Comma) => self.error(ErrorKind::UnexpectedToken(","), column),
+            Some(Token::Assign) => self.error(ErrorKind::UnexpectedToken("="), column),
+            Some(Token::Operator(_)) | None => self.error(ErrorKind::ExpectedNumber, column)
+        }
+    }
+}
+
+/*- The radix of a number like 0xff, and its digits -*/
+fn radix(input:&str) -> (u32, &str) {
+    let radix = match input.get(..2).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("0x") => 16,
+        Some("0b") => 2,
+        Some("0o") => 8,
+        _ => return (10, input)
+    };
+    (radix, &input[2..])
+}
+
+/// Parse a number like `12`, `0.5`, `1e-5` or `0xff`
+pub fn parse_number(input:&str) -> Result<f64, ErrorKind> {
+    let (radix, digits) = radix(input);
+    if radix != 10 {
+        return match u64::from_str_radix(digits, radix) {
+            Ok(number) => Ok(number as f64),
+            Err(error) if *error.kind() == std::num::IntErrorKind::PosOverflow => Err(ErrorKind::Overflow),
+            Err(_) => Err(ErrorKind::InvalidNumber(input.to_string()))
+        };
+    };
+
+    let valid = input.chars().next().is_some_and(|c| c.is_ascii_digit() || c == '.')
+        && !input.ends_with(['e', 'E', '+', '-']);
+
+    match input.parse::<f64>() {
+        Ok(number) if valid && number.is_infinite() => Err(ErrorKind::Overflow),
+        Ok(number) if valid => Ok(number),
+        _ => Err(ErrorKind::InvalidNumber(input.to_string()))
+    }
+}
+
+/// Parse and work out a calculation that isn't an assignment
+pub fn evaluate<N:Number>(input:&str, variables:&HashMap<String, String>) -> Result<N, CalcError> {
+    match parse(input)? {
+        (None, expr) => expr.evaluate(variables),
+
+        /*- The '=' comes right after the name -*/
+        (Some(_), _) => {
+            let column = input.find('=').map_or(1, |e| input[..e].chars().count() + 1);
+            Err(CalcError { kind: ErrorKind::UnexpectedToken("="), column })
+        }
+    }
+}
+
+/// Show a result without float noise, so 0.1 + 0.2 is 0.3
+pub fn format(number:f64) -> String {
+    if !number.is_finite() { return number.to_string() };
+
+    /*- Whole numbers are exact, rounding would turn 2^53 into 9007199254740990 -*/
+    if number.fract() == 0.0 && number.abs() < 2f64.powi(63) {
+        return (number as i64).to_string();
+    };
+
+    /*- 15 significant digits is what a f64 can always hold -*/
+    format!("{number:.14e}").parse::<f64>().unwrap_or(number).to_string()
+}
+
+/// Parse a calculation like `(1 + 2) * 3`, or an assignment like
+/// `total = price * 2` which also gives back the name to store it in
+pub fn parse(input:&str) -> Result<(Option<String>, Expr), CalcError> {
+    let mut tokens = lex(input)?;
+    let end = input.chars().count() + 1;
+
+    /*- name = calculation -*/
+    let target = match tokens.as_slice() {
+        [(Token::Identifier(name), _), (Token::Assign, _), ..] => {
+            let name = name.to_string();
+            tokens.drain(..2);
+            Some(name)
+        },
+        _ => None
+    };
+
+    let mut parser = Parser { tokens, pos: 0, end, depth: 0 };
+    let expr = parser.expression(0)?;
+
+    /*- Everything has to be used -*/
+    let column = parser.column();
+    match parser.next() {
+        None => Ok((target, expr)),
+        Some(Token::CloseParen) => parser.error(ErrorKind::UnexpectedToken(")"), column),
+        Some(Token::Comma) => parser.error(ErrorKind::UnexpectedToken(","), column),
+        Some(Token::Assign) => parser.error(ErrorKind::UnexpectedToken("="), column),
+        Some(_) => parser.error(ErrorKind::ExpectedOperator, column)
+    }
+}
+
+/// What 'help calc' shows after the description
+pub fn help(line_break:&str) -> String {
+    let functions = FUNCTIONS.iter().map(|(name, arity, _, description)| {
+        let arguments = match arity {
+            Some(1) => "x",
+            Some(2) => "x, y",
+            _ => "x, ...",
+        };
+        format!("{name}({arguments}) - {description}")
+    });
+    let constants = CONSTANTS.iter().map(|(name, value)| format!("{name} = {value}"));
+
+    std::iter::once(String::from("Functions:"))
+        .chain(functions)
+        .chain(std::iter::once(String::from("Constants:")))
+        .chain(constants)
+        .collect::<Vec<String>>()
+        .join(line_break)
+}
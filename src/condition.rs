@@ -0,0 +1,312 @@
+/*- Conditions used by 'if', 'while' and 'until', like (a > 1 && b < 5) || !done or
+    name startswith jo. The values being compared are worked out
+    by the calc engine -*/
+
+/*- Imports -*/
+use crate::calc::{self, CalcError, ErrorKind};
+use regex::Regex;
+use std::{cmp::Ordering, collections::HashMap};
+
+/*- One side of a comparison. The text is what was written, or the
+    value of the variable that was named, so 'hello contains e' looks
+    at the letter e and not the number. The number is there if the
+    text is a calculation -*/
+#[derive(Debug, Clone, PartialEq)]
+struct Value {
+    text: String,
+    number: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+    StartsWith,
+    EndsWith,
+
+    /// `=~`, the right side is a regex
+    Matches,
+}
+
+impl Comparison {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Contains => "contains",
+            Comparison::StartsWith => "startswith",
+            Comparison::EndsWith => "endswith",
+            Comparison::Matches => "=~",
+        }
+    }
+
+    /*- 'contains' and friends are words, the rest are symbols -*/
+    fn is_word(&self) -> bool {
+        self.symbol().starts_with(|c:char| c.is_ascii_alphabetic())
+    }
+
+    /*- Two numbers are compared as numbers, anything else as text -*/
+    fn compare(&self, left:&Value, right:&Value) -> Result<bool, ErrorKind> {
+        let ordering = match (left.number, right.number) {
+            (Some(l), Some(r)) => l.partial_cmp(&r),
+            _ => Some(left.text.cmp(&right.text))
+        };
+        let (left, right) = (&left.text, &right.text);
+
+        Ok(match self {
+            Comparison::Equal => ordering == Some(Ordering::Equal),
+            Comparison::NotEqual => ordering != Some(Ordering::Equal),
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+            Comparison::GreaterOrEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            Comparison::Contains => left.contains(right.as_str()),
+            Comparison::StartsWith => left.starts_with(right.as_str()),
+            Comparison::EndsWith => left.ends_with(right.as_str()),
+            Comparison::Matches => match Regex::new(right) {
+                Ok(regex) => regex.is_match(left),
+                Err(_) => return Err(ErrorKind::Invalid(format!("Invalid regex '{right}'")))
+            },
+        })
+    }
+}
+
+/*- Longer symbols first, so '<=' isn't read as '<' -*/
+const COMPARISONS:&[Comparison] = &[
+    Comparison::Equal,
+    Comparison::NotEqual,
+    Comparison::LessOrEqual,
+    Comparison::GreaterOrEqual,
+    Comparison::Matches,
+    Comparison::Less,
+    Comparison::Greater,
+    Comparison::Contains,
+    Comparison::StartsWith,
+    Comparison::EndsWith,
+];
+
+/*- Recursive descent over the characters, with
+    || binding loosest, then &&, then ! -*/
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    variables: &'a HashMap<String, String>,
+
+    /*- Off for the parts && and || skip, which are only parsed -*/
+    evaluate: bool,
+
+    /*- How many '!' and groups the parser is inside of -*/
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, pattern:&str) -> bool {
+        pattern.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) { self.pos += 1; };
+    }
+
+    /*- Skip over 'pattern' if it comes next -*/
+    fn eat(&mut self, pattern:&str) -> bool {
+        self.skip_whitespace();
+        let found = self.starts_with(pattern);
+        if found { self.pos += pattern.chars().count(); };
+        found
+    }
+
+    fn error<T>(&self, kind:ErrorKind, column:usize) -> Result<T, CalcError> {
+        Err(CalcError { kind, column })
+    }
+
+    /*- The comparison that comes next, without skipping it. '<<'
+        and '>>' are shifts for the calc engine, not comparisons, and
+        words like 'contains' need whitespace around them -*/
+    fn comparison_ahead(&self) -> Option<Comparison> {
+        if self.starts_with("<<") || self.starts_with(">>") { return None; };
+
+        let after_space = self.pos > 0 && self.chars[self.pos - 1].is_whitespace();
+        COMPARISONS.iter().find(|e| {
+            let followed_by_space = || self.chars.get(self.pos + e.symbol().len()).is_some_and(|c| c.is_whitespace());
+            self.starts_with(e.symbol()) && (!e.is_word() || (after_space && followed_by_space()))
+        }).copied()
+    }
+
+    /*- Parse with 'part', but only work it out if it's 'needed'. Keeps
+        guards like n != 0 && 10 / n > 2 from dividing by zero -*/
+    fn part(&mut self, needed:bool, part:fn(&mut Self) -> Result<bool, CalcError>) -> Result<bool, CalcError> {
+        let outer = self.evaluate;
+        self.evaluate = outer && needed;
+        let result = part(self);
+        self.evaluate = outer;
+        result
+    }
+
+    fn or(&mut self) -> Result<bool, CalcError> {
+        let mut value = self.and()?;
+        while self.eat("||") {
+            let right = self.part(!value, Self::and)?;
+            value = value || right;
+        };
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<bool, CalcError> {
+        let mut value = self.not()?;
+        while self.eat("&&") {
+            let right = self.part(value, Self::not)?;
+            value = value && right;
+        };
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<bool, CalcError> {
+        self.skip_whitespace();
+        if self.depth == calc::MAX_NESTING { return self.error(ErrorKind::TooDeep, self.pos + 1); };
+        self.depth += 1;
+
+        /*- Not the start of '!=' -*/
+        let result = match self.starts_with("!") && !self.starts_with("!=") {
+            true => {
+                self.pos += 1;
+                self.not().map(|e| !e)
+            },
+            false => self.group()
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /*- A '(' can group conditions, or be part of a calculation
+        like (a + 1) * 2 > 3. It's only a group if a whole condition
+        fits inside and nothing but && or || comes after it -*/
+    fn group(&mut self) -> Result<bool, CalcError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        if self.eat("(") {
+            if let Ok(value) = self.or() {
+                if self.eat(")") {
+                    self.skip_whitespace();
+                    if self.peek().is_none() || self.peek() == Some(')') || self.starts_with("&&") || self.starts_with("||") {
+                        return Ok(value);
+                    };
+                };
+            };
+            self.pos = start;
+        };
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<bool, CalcError> {
+        self.skip_whitespace();
+        let start = self.pos + 1;
+        let left = self.operand()?;
+
+        self.skip_whitespace();
+        let column = self.pos + 1;
+        let comparison = match self.comparison_ahead() {
+            Some(comparison) => comparison,
+            None if !self.evaluate => return Ok(false),
+            None => return truthy(&left).map_err(|kind| CalcError { kind, column: start })
+        };
+        self.pos += comparison.symbol().chars().count();
+        let right = self.operand()?;
+
+        /*- a == b == c would be ambiguous -*/
+        self.skip_whitespace();
+        if self.comparison_ahead().is_some() {
+            return self.error(ErrorKind::Invalid(String::from("Comparisons can't be chained, join them with &&")), self.pos + 1);
+        };
+        if !self.evaluate { return Ok(false); };
+
+        comparison.compare(&left, &right).map_err(|kind| CalcError { kind, column })
+    }
+
+    /*- Everything up to the next comparison, && or ||, or the
+        ')' that closes a group -*/
+    fn operand(&mut self) -> Result<Value, CalcError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut open:Vec<usize> = Vec::new();
+
+        while let Some(c) = self.peek() {
+            if open.is_empty() && (c == ')' || self.starts_with("&&") || self.starts_with("||") || self.comparison_ahead().is_some()) {
+                break;
+            };
+
+            match c {
+                '(' => open.push(self.pos),
+                ')' => { open.pop(); },
+                _ if self.starts_with("<<") || self.starts_with(">>") => self.pos += 1,
+                _ => ()
+            };
+            self.pos += 1;
+        };
+
+        if let Some(paren) = open.first() {
+            return self.error(ErrorKind::MissingParen, paren + 1);
+        };
+
+        let text = self.chars[start..self.pos].iter().collect::<String>();
+        match text.trim_end() {
+            "" => self.error(ErrorKind::Invalid(String::from("Expected a value")), start + 1),
+            text if !self.evaluate => Ok(Value { text: text.to_string(), number: None }),
+            text => self.value(text, start + 1)
+        }
+    }
+
+    /*- Calculations and numeric variables also get a number -*/
+    fn value(&self, text:&str, column:usize) -> Result<Value, CalcError> {
+        let number = match calc::evaluate::<f64>(text, self.variables) {
+            Ok(number) => Some(number),
+
+            /*- Math that goes wrong is still an error -*/
+            Err(CalcError { kind: kind @ (ErrorKind::DivisionByZero | ErrorKind::Overflow | ErrorKind::TooDeep), column: inner }) => {
+                return self.error(kind, column + inner - 1);
+            },
+            Err(_) => None
+        };
+
+        let text = self.variables.get(text).cloned().unwrap_or_else(|| text.to_string());
+        Ok(Value { text, number })
+    }
+}
+
+/*- A value on its own, like 'true' or a variable holding 'false' -*/
+fn truthy(value:&Value) -> Result<bool, ErrorKind> {
+    match (value.number, value.text.as_str()) {
+        (Some(number), _) => Ok(number != 0.0),
+        (None, "true") => Ok(true),
+        (None, "false") => Ok(false),
+        (None, text) => Err(ErrorKind::Invalid(format!("'{text}' isn't true or false")))
+    }
+}
+
+/// Work out a condition like `(a > 1 && b < 5) || !done`. Names are
+/// looked up in `variables`, like they are in calculations
+pub fn evaluate(input:&str, variables:&HashMap<String, String>) -> Result<bool, CalcError> {
+    let mut parser = Parser { chars: input.chars().collect(), pos: 0, variables, evaluate: true, depth: 0 };
+    let value = parser.or()?;
+
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(value),
+        Some(_) => parser.error(ErrorKind::UnexpectedToken(")"), parser.pos + 1)
+    }
+}
@@ -6,245 +6,196 @@
     // non-uppercase when it actually shouldn't be
     non_upper_case_globals,
     dead_code,
-    unused_imports
+    unused_imports,
+
+    // Explicit returns and named outputs are how this
+    // crate is written, clippy doesn't need to mention it
+    clippy::needless_return,
+    clippy::let_and_return
 )]
 
-#[wasm_bindgen]
-extern "C" {
-    // Use `js_namespace` here to bind `console.log(..)` instead of just
-    // `log(..)`
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-}
+/*- Modules -*/
+mod parser;
+mod terminal;
+mod result;
+mod calc;
+mod units;
+mod stats;
+mod plot;
+mod condition;
+pub mod platform;
 
 /*- Imports -*/
 use js_sys::{self, Math::pow};
-use reqwest;
-use regex::{Regex, Captures};
-use std::{sync::Mutex, num, future::Future, pin::Pin, str::ParseBoolError};
+use regex::Regex;
+use std::{sync::Mutex, num, future::Future, pin::Pin};
 use lazy_static::lazy_static;
 use wasm_bindgen::prelude::*;
 use std::collections::HashMap;
+use parser::Node;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+pub use terminal::{Terminal, Config};
+pub use result::{CommandResult, Event};
+
+/*- The session used by the free `command` function -*/
+lazy_static! {
+    static ref SESSION:Mutex<Terminal> = Mutex::new(Terminal::new());
+}
 
-/*- Mutable global arrays -*/
+/*- Patterns are compiled once instead of on every command -*/
 lazy_static! {
-    static ref VARIABLES:Mutex<Vec<(String, String)>> = Mutex::new(vec![]);
-    static ref FUNCTIONS:Mutex<Vec<(String, String, Vec<String>)>> = Mutex::new(vec![]);
+    /*- name(params) in 'fn' and 'exec' -*/
+    static ref FN_NAME:Regex = Regex::new(r"(.+?)\((.*?|)\)").unwrap();
 }
 
+/*- Every built-in gets the session and the unevaluated argument nodes -*/
+type Builtin = fn(&mut Terminal, &[Node]) -> CommandResult;
+
 /*- Commands are listed here -*/
-const COMMANDS:&[(&str, fn(Vec<&str>) -> String, &str); 17] = &[
-    ("return", _return, "Print text to the terminal. Example: |return hello world!|"),
-    ("repeat", _repeat, "Repeat commands x number of times. Example: |repeat 10 i return index: #i|"),
+const COMMANDS:&[(&str, Builtin, &str); 32] = &[
+    ("return", _return, "Print text to the terminal. Without any text it prints what was piped into it. Example: |return hello world!|"),
+    ("repeat", _repeat, "Repeat commands x number of times. Example: |repeat 10 i return index: #i|, |repeat 3 i {return a #i && return b #i}|"),
     ("help", _help, "|help| will list all commands. |help command_name| will give a description of how you use that command."),
-    ("set", _set, "Set a variable. |set variable_name variable_value|"),
+    ("set", _set, "Set a variable. |set variable_name variable_value|, |set greeting \"hello <name>\"|, |set raw 'not <replaced>'|"),
     ("get", _get, "Get a variable. |get variable_name|"),
     ("ol", _ol, "Runs commands, but makes their output one-line. Example: |ol repeat 15 return hello|"),
     ("olc", _olc, "Runs commands, but makes their output one-line, without spaces. Example: |ol repeat 15 return hello|"),
-    ("fn", _fn, "Create a function. Example: |fn function_name(param1,param2) return p1: --param1, p2: --param2|"),
+    ("fn", _fn, "Create a function. Example: |fn function_name(param1,param2) return p1: --param1, p2: --param2|, |fn greet(name) {return hi --name && return bye --name}|"),
     ("exec", _exec, "Execute a function. Example: |exec function_name(param1,param2)|"),
     ("list", _list, "List global variables. Example: |list vars|, |list cmd|, |list fn|"),
-    ("replace", _replace, "Replace strings inside of a string. Example: |replace hello lo loooo|, |replace hi hi :space: :nothing:|"),
+    ("history", _history, "List previously run commands. Example: |history|, |history 5|"),
+    ("replace", _replace, "Replace strings inside of a string. Without a string it replaces in what was piped into it. Example: |replace hello lo loooo|, |replace hi hi :space: :nothing:|"),
     ("random", _random, "Get a random number. Example: |random 1 100|"),
-    ("calc", _calc, "Calculate things. Example: |calc 5 * 2 + 1 - 4 / 5|"),
-    ("if", _if, "Execute a commands depending on a condition. Example: |if (eval(calc 5 * 5) == 25) {return yes} else {return this will never be called}|"),
+    ("calc", _calc, "Calculate things. Without a calculation it calculates what was piped into it. Powers are written with ^, ** or !. Whole numbers can be written as 0xff, 0b101 or 0o17, and use the bitwise operators & | xor ~ << >> (put | inside quotes or parentheses). Calculations with only whole numbers are exact, as long as they fit in a float (up to about 1.8e308, bigger is an overflow). |calc --int| only allows whole numbers, |calc --frac| does exact math on fractions (add --dec to show the result as a decimal number). Variables can be used by name, |calc name = ...| sets one and |ans| is the last result. Example: |calc (5 + 2) * -3 ^ 2 % 4 + sqrt(2) * pi|"),
+    ("base", _base, "Write a whole number in another base, from 2 to 36. Example: |base 255 16|, |base 0xff 2|"),
+    ("convert", _convert, "Convert between units of length, mass, volume, temperature, time, data size and speed. The amount can be a calculation. Example: |convert 5 km to mi|, |convert 2^10 KiB to MB|"),
+    ("plot", _plot, "Draw a calculation in x as a graph. The range of x is -10 to 10 unless given after the calculation, and the size can be set with |--width| and |--height|. Example: |plot sin(x) -pi pi|, |plot x^2 - 2*x --width 60 --height 20|"),
+    ("stats", _stats, "Summarize numbers, separated by spaces or commas. Without numbers it uses what was piped into it. |--sample| uses the sample variance. Example: |stats 1, 2, 2, 5|"),
+    ("sum", _sum, "Add numbers together. Example: |sum 1 2 3|"),
+    ("mean", _mean, "The average of numbers. Example: |mean 1 2 3|"),
+    ("median", _median, "The middle number. Example: |median 5 1 3 2|"),
+    ("mode", _mode, "The most common numbers. Example: |mode 1 2 2 3|"),
+    ("variance", _variance, "How spread out numbers are. |--sample| uses the sample variance. Example: |variance 1 2 3 4|"),
+    ("stddev", _stddev, "Standard deviation of numbers. |--sample| uses the sample standard deviation. Example: |stddev --sample 1 2 3 4|"),
+    ("percentile", _percentile, "The value below which a percentage of the numbers fall. Example: |percentile 90 1 2 3 4 5|"),
+    ("minmax", _minmax, "The smallest and biggest number. Example: |minmax 4 -1 7|"),
+    ("if", _if, "Execute a commands depending on a condition. Any number of |else if (...) {...}| can follow, and the |else {...}| can be left out. Conditions compare with == != < <= > >=, as numbers if both sides are numbers and as text otherwise, or with contains, startswith, endswith and =~ (regex), and can be combined with && || ! and parentheses. Variables can be used by name. Example: |if (eval(calc 5 * 5) == 25) {return yes} else {return this will never be called}|, |if ((a > 1 && b < 5) || !done) {return yes} else {return no}|, |if (name =~ '^jo') {return hi} else if (n >= 10) {return ten} else {return bye}|"),
+    ("while", _while, "Execute commands as long as a condition is true, checking it before every run. Conditions work like in |if|. Example: |while (n > 0) {calc n = n - 1}|"),
+    ("until", _until, "Execute commands until a condition is true, checking it before every run. Conditions work like in |if|. Example: |until (n == 1) {calc n = n / 2}|"),
 
     // These functions are defined in the js-side.
-    ("reset", |name| { String::new() }, "[JS-SIDE] Clears the terminal. Variables are still kept."),
-    ("theme", |name| { String::new() }, "[JS-SIDE] Changes the theme. Example: |theme aqua|"),
-    ("full",  |name| { String::new() }, "[JS-SIDE] Toggles fullscreen."),
+    // They only send an event for the js-side to act on.
+    ("reset", |_, name| { CommandResult::default().with_event(Event::Clear) }, "[JS-SIDE] Clears the terminal. Variables are still kept."),
+    ("theme", |term, name| { CommandResult::default().with_event(Event::Theme(term.expand_all(name).join(" "))) }, "[JS-SIDE] Changes the theme. Example: |theme aqua|"),
+    ("full",  |_, name| { CommandResult::default().with_event(Event::Fullscreen) }, "[JS-SIDE] Toggles fullscreen."),
 ];
 
-/*- Call commands -*/
+/*- Call commands in the default session -*/
 #[wasm_bindgen]
-pub fn command(input:String) -> String {
-    let mut output:Vec<String> = Vec::new();
-    let mut fn_found:bool = false;
-
-    /*- A command can contain '&&' which will make users
-        be able to execute multiple commands in one line -*/
-    for command_ in input.split("&&") {
-
-        /*- Get the command name -*/
-        let command_name = command_.split_whitespace().nth(0).unwrap_or("");
-        
-        /*- Find the command and call it -*/
-        'inner: for (command, caller, _) in COMMANDS {
-            if &command_name == command {
-                
-                /*- Get the input -*/
-                let command_ = replace_info(command_.to_string());
-                
-                /*- Get the args -*/
-                let argv:Vec<&str> = command_.split_whitespace().skip(1).collect();
-                
-                /*- Call the function -*/
-                output.push(caller(argv));
-                fn_found = true;
-                break 'inner;
-            };
-        };
-
-        // /*- Get the possible function name -*/
-        // let fn_name_regex:Regex = Regex::new(r"(.+?)\(").unwrap().captures(command_name).unwrap().get(1).unwrap().as_str().parse().unwrap();
-
-        // /*- If no builtin-command was found, check for user-created functions -*/
-        // for (fn_name, _, __) in FUNCTIONS.lock().unwrap().iter() {
-        //     if fn_name == command_name {
-        //         output.push(_exec(command_.split_whitespace().collect()));
-        //     }
-        // }
-    };
+pub fn command(input:String) -> CommandResult {
+    SESSION.lock().unwrap().run(input)
+}
 
-    /*- Return -*/
-    if fn_found { return output.join("<br />"); };
-    "Command not found!".to_string()
+/*- Commands like |repeat| and |fn| take the rest of their
+    arguments as a command, or a single block -*/
+fn body(nodes:&[Node]) -> Node {
+    match nodes {
+        [Node::Block(inner)] => *inner.clone(),
+        _ => Node::Command(nodes.to_vec())
+    }
 }
 
 /*- Replace info is a function that replaces
     things like :date: with the actual date -*/
 #[wasm_bindgen]
 pub fn replace_info(input:String) -> String {
-
-    /*- Quick replacements -*/
-    let mut input = input
-        .replace("\\n", "<br />")
-        .replace("\\_", " ");
-
-    /*- Regex for random number gen, can be called like this - :random 10-124: which will replace the input with something like 23 -*/
-    let random_regex = Regex::new(r":random\s([0-9]+)-([0-9]+):").unwrap();
-
-    /*- Typing 'var(variable_name)' will replace it with the value -*/
-    let var_regex = Regex::new(r"var\((.+?)\)").unwrap();
-
-    /*- Typing 'replace(string,replace,with)' will replace all 'replace' with 'with' -*/
-    let replace_regex = Regex::new(r"replace\((.+?)\)").unwrap();
-
-    /*- Replace all variables -*/
-    for (k, v) in VARIABLES.lock().unwrap().clone() {
-        input = input.replace(
-            &format!("<{k}>"),
-            &v
-        );
-    };
-    
-    /*- Make the random replacement -*/
-    let input = random_regex.replace_all(&input, |caps: &regex::Captures| {
-        let min = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
-        let max = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
-        let random = (js_sys::Math::random() * (max - min)as f64 + min as f64) as i32;
-        random.to_string()
-    }).to_string();
-
-    /*- Make the 'var' replacement -*/
-    let input = var_regex.replace_all(&input, |caps: &regex::Captures| {
-        /*- The 0:th capture is the whole thing, the 1:st one is the command -*/
-        match caps.get(1) {
-            Some(capture) => variable(capture.as_str()),
-            None => String::new()
-        }
-    }).to_string();
-
-    /*- Make the 'replace' replacement -*/
-    let input = replace_regex.replace_all(&input, |caps: &regex::Captures| {
-        /*- The 0:th capture is the whole thing, the 1:st one is the command -*/
-        match caps.get(1) {
-            Some(capture) => {
-                let caps_:Vec<&str> = capture.as_str().split(",").collect();
-                if caps_.len() < 3 { return String::from("Invalid replace command!") };
-
-                (caps_[0].replace(caps_[1], caps_[2])).to_string()
-            },
-            None => "".to_string()
-        }
-    }).to_string();
-
-    /*- Return -*/
-    input
-}
-
-fn eval_string(input:String) -> String {
-
-    /*- Typing 'eval(some_command)' will replace it with the output of the command -*/
-    let eval_regex = Regex::new(r"eval\((.+?)\)").unwrap();
-
-    /*- Make the 'eval' replacement -*/
-    let input = eval_regex.replace_all(&input, |caps: &regex::Captures| {
-        /*- The 0:th capture is the whole thing, the 1:st one is the command -*/
-        command(match caps.get(1) {
-            Some(capture) => capture.as_str().to_string(),
-            None => String::new()
-        })
-    }).to_string();
-
-    input
+    SESSION.lock().unwrap().replace_info(input)
 }
 
 /*- All commands -*/
 /// Print something to stdout
-pub fn _return(input:Vec<&str>) -> String {
-    return
-        input.join(" ");
+pub fn _return(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    /*- Without arguments, print what was piped in -*/
+    if input.is_empty() {
+        return CommandResult::ok(term.take_stdin().unwrap_or_default());
+    };
+
+    return CommandResult::ok(
+        term.expand_all(input).join(" ")
+    );
 }
 
 /// Repeat some code
-pub fn _repeat(input:Vec<&str>) -> String {
+pub fn _repeat(term:&mut Terminal, input:&[Node]) -> CommandResult {
     /*- The amount of times the code will repeat -*/
     /*- If num of repeat was specified -*/
-    let num_of_repeat:i32 = match input.get(0) {
-        Some(num) => num.parse::<i32>().unwrap_or(1),
-        None => return String::from("Num-repeat not specified! Type |help repeat| for further info.")
+    let num_of_repeat:i32 = match input.first() {
+        Some(num) => term.expand(num).parse::<i32>().unwrap_or(1),
+        None => return CommandResult::err("Num-repeat not specified! Type |help repeat| for further info.")
     };
 
     /*- Get what the user wants to name the index -*/
-    let index_name:&str = match input.get(1) {
-        Some(name) => name,
-        None => return String::from("Index not specified! Type |help repeat| for further info.")
+    let index_name:String = match input.get(1) {
+        Some(name) => term.expand(name),
+        None => return CommandResult::err("Index not specified! Type |help repeat| for further info.")
     };
 
     /*- Check if the command was specified -*/
-    if input.len() <= 2 { return String::from("No command to repeat was specified! Type |help repeat| for further info.") };
+    if input.len() <= 2 { return CommandResult::err("No command to repeat was specified! Type |help repeat| for further info.") };
 
     /*- Get the command and its arguments -*/
-    let _command = &input[2..].join(" ");
+    let _command = body(&input[2..]);
 
     /*- The output of all commands -*/
-    let mut output:Vec<String> = Vec::with_capacity(num_of_repeat as usize);
+    let mut output = CommandResult::default();
 
     /*- Repeat the command -*/
     for i in 0..num_of_repeat {
-        output.push(
-            command(
-                /*- We'll replace the --i flag with the index -*/
-                eval_string(_command.replace(
+        /*- Every pass uses up a step, so even an empty body stops
+            at the step limit. Also stops after the output limit -*/
+        if !term.step() { break; };
+
+        let result = term.run_node(
+                /*- We'll replace the #i flag with the index -*/
+                &_command.replace(
                     &format!(
                         "#{}",
                         index_name
                     ),
                     &i.to_string()
-                )).to_string()
-            )
-        )
+                )
+            );
+        term.append(&mut output, result);
     };
 
-    output.join("<br />")
+    output
 }
 
 /// Help with commands
-pub fn _help(input:Vec<&str>) -> String {
+pub fn _help(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
     let mut out = Vec::new();
 
     /*- See if the user wants a description of a command -*/
-    match input.get(0) {
+    match input.first() {
         Some(command_name) => {
             /*- Find the command -*/
             for (name, _, description) in COMMANDS {
                 if name == command_name {
-                    return description.to_string();
+                    /*- Some commands have more to list -*/
+                    let line_break = &term.config().line_break;
+                    let details = match *name {
+                        "calc" => calc::help(line_break),
+                        "convert" => units::help(line_break),
+                        _ => return CommandResult::ok(*description)
+                    };
+
+                    return CommandResult::ok(format!("{description}{line_break}{details}"));
                 }
             };
 
-            return format!("No such command: '{command_name}'");
+            return CommandResult::err(format!("No such command: '{command_name}'"));
         },
         None => {
             /*- Get all command names -*/
@@ -255,403 +206,595 @@ pub fn _help(input:Vec<&str>) -> String {
     }
 
     /*- Return -*/
-    format!(
-        "{}<br />{}",
+    CommandResult::ok(format!(
+        "{}{}{}",
         "Type |help command_name| for further info on each command",
+        term.config().line_break,
         out.join(" - "),
-    )
+    ))
 }
 
 // Get variables
-pub fn _get(input:Vec<&str>) -> String {
-    let variable_name = &input.get(0).unwrap_or(&"");
+pub fn _get(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let variable_name = input.first().map(|e| term.expand(e)).unwrap_or_default();
 
     /*- Get the variable value -*/
-    variable(variable_name)
+    CommandResult::ok(term.variable(&variable_name))
 }
 
 // Set variables
-pub fn _set(input:Vec<&str>) -> String {
+pub fn _set(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
 
-    /*- Set the variable -*/
-    VARIABLES.lock().unwrap().push((
-        input.get(0).unwrap_or(&"").to_string(),
-        input.get(1).unwrap_or(&"").to_string(),
-    ));
+    /*- Set the variable. Everything after the name is the value -*/
+    term.set_variable(
+        input.first().cloned().unwrap_or_default(),
+        input.get(1..).unwrap_or_default().join(" "),
+    );
 
     /*- Return success -*/
-    String::from("Success")
+    CommandResult::ok("Success")
 }
 
 // Command with one-line output
-pub fn _ol(input:Vec<&str>) -> String {
-    let output = command(input.join(" "))
-                .replace("<br />", " ")
-                .replace("\n", "");
+pub fn _ol(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let mut result = term.run_node(&body(input));
+    let output = result.output_str()
+                .replace(&term.config().line_break, " ")
+                .replace("\n", " ");
     
-    output
+    result.set_output(output);
+    result
 }
 // Command with one-line output (without spaces)
-pub fn _olc(input:Vec<&str>) -> String {
-    let output = command(input.join(" "))
-                .replace("<br />", "")
+pub fn _olc(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let mut result = term.run_node(&body(input));
+    let output = result.output_str()
+                .replace(&term.config().line_break, "")
                 .replace("\n", "");
     
-    output
+    result.set_output(output);
+    result
 }
 
 // Create function
-pub fn _fn(input:Vec<&str>) -> String {
+pub fn _fn(term:&mut Terminal, input:&[Node]) -> CommandResult {
     /*- Get the function name -*/
     /*- If fnname was specified -*/
-    let fn_name:String = match input.get(0) {
-        Some(name) => name.to_string(),
-        None => return String::from("Function name not specified! Type |help fn| for further info.")
+    let fn_name:String = match input.first() {
+        Some(name) => term.expand(name),
+        None => return CommandResult::err("Function name not specified! Type |help fn| for further info.")
     };
 
     /*- fn_name will look like this: name(params),
         so we'll extract the params from the name -*/
-    let name_regex:Regex = Regex::new(r"(.+?)\((.*?|)\)").unwrap();
-    let name_captures = match name_regex.captures(&fn_name) {
+    let name_captures = match FN_NAME.captures(&fn_name) {
         Some(n) => n,
-        None => return String::from("Invalid fn declaration! Type |help fn| for further info.")
+        None => return CommandResult::err("Invalid fn declaration! Type |help fn| for further info.")
     };
     
     let (fn_name, params): (String, Vec<String>) = (
         match name_captures.get(1) {
             Some(string) => string.as_str().to_string(),
-            None => return String::from("Invalid fn declaration! Type |help fn| for further info.")
+            None => return CommandResult::err("Invalid fn declaration! Type |help fn| for further info.")
         },
         match name_captures.get(2) {
             Some(string) => string.as_str().split(",").map(|e| e.trim().to_string()).collect::<Vec<String>>(),
-            None => return String::from("Invalid fn declaration! Type |help fn| for further info.")
+            None => return CommandResult::err("Invalid fn declaration! Type |help fn| for further info.")
         }        
     );
 
     /*- Check if function-name is reserved -*/
     for (name, _, __) in COMMANDS {
         if name == &fn_name {
-            return format!("Function name '{}' is reserved!", fn_name);
+            return CommandResult::err(format!("Function name '{}' is reserved!", fn_name));
         };
     };
 
     /*- Check if the command was specified -*/
-    if input.len() <= 1 { return String::from("No command was specified! Type |help fn| for further info.") };
+    if input.len() <= 1 { return CommandResult::err("No command was specified! Type |help fn| for further info.") };
 
     /*- Get the command and its arguments -*/
-    let _command = &input[1..].join(" ");
-    let _command = _command.replace("__AND__", "&&");
+    let _command = body(&input[1..]);
 
     /*- Set the functiom -*/
-    FUNCTIONS.lock().unwrap().push(( fn_name, _command.to_string(), params ));
+    term.set_function(( fn_name, _command, params ));
 
-    String::from("Success!")
+    CommandResult::ok("Success!")
 }
 
 // Call function
-pub fn _exec(input:Vec<&str>) -> String {
-    let function = &input.get(0).unwrap_or(&"");
-    let name_regex:Regex = Regex::new(r"(.+?)\((.*?|)\)").unwrap();
-    log("1");
-    let name_captures = match name_regex.captures(&function) {
+pub fn _exec(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let function = input.first().map(|e| term.expand(e)).unwrap_or_default();
+    let name_captures = match FN_NAME.captures(&function) {
         Some(n) => n,
-        None => return String::from("Invalid fn declaration! Type |help fn| for further info.")
+        None => return CommandResult::err("Invalid fn declaration! Type |help fn| for further info.")
     };
     let (fn_name, params): (String, Vec<String>) = (
         match name_captures.get(1) {
             Some(string) => string.as_str().to_string(),
-            None => return String::from("Invalid exec declaration! Type |help exec| for further info.")
+            None => return CommandResult::err("Invalid exec declaration! Type |help exec| for further info.")
         },
         match name_captures.get(2) {
-            Some(string) => string.as_str().split(",").map(|e| eval_string(e.trim().to_string())).collect::<Vec<String>>(),
-            None => return String::from("Invalid exec declaration! Type |help exec| for further info.")
+            Some(string) => string.as_str().split(",").map(|e| e.trim().to_string()).collect::<Vec<String>>(),
+            None => return CommandResult::err("Invalid exec declaration! Type |help exec| for further info.")
         }  
     );
 
 
-    /*- Get the variable -*/
-    for (k, v, p) in FUNCTIONS.lock().unwrap().clone() {
-        log("Search");
-        if k == fn_name {
-
-            log("3.1");
-
-            /*- Get the function arguments -*/
-            let mut final_command:String = v;
+    /*- Get the function -*/
+    if let Some((_, v, p)) = term.function(&fn_name).cloned() {
 
-            log("3.2");
-
-            /*- Replace all params -*/
-            for (index, arg) in p.iter().enumerate() {
-                let param_value = params.get(index).unwrap_or(&String::new()).clone();
-                log("3.3");
-                final_command = final_command.replace(
-                    &format!("--{arg}"), &param_value
-                );
-                log("3.4");
-            };
-            log("3.5");
+        /*- Get the function arguments -*/
+        let mut final_command:Node = v;
 
-            return command(
-                final_command
+        /*- Replace all params -*/
+        for (index, arg) in p.iter().enumerate() {
+            let param_value = params.get(index).cloned().unwrap_or_default();
+            final_command = final_command.replace(
+                &format!("--{arg}"), &param_value
             );
         };
+
+        return term.run_node(
+            &final_command
+        );
     };
-    log("4");
 
     /*- Return else -*/
-    String::from("null")
+    CommandResult::err(format!("No such function: '{fn_name}'"))
 }
 
 // List globals
-pub fn _list(input:Vec<&str>) -> String {
-    let what_to_list = &input.get(0).unwrap_or(&"");
+pub fn _list(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let what_to_list = input.first().map(|e| term.expand(e)).unwrap_or_default();
 
     /*- Check what the user wants to list -*/
-    match what_to_list {
-        &&"var" => {
-            return VARIABLES
-                .lock() // Get the array from the mutex guard
-                .unwrap()
-                .clone()
-                .into_iter() // Make it an iterator
-                .map(|(e, v)| e) // Get the key from the tuple
-                .collect::<Vec<String>>() // Make it into an array
-                .join(" | "); // Make it into a string
+    match what_to_list.as_str() {
+        "var" => {
+            return CommandResult::ok(term
+                .variables()
+                .keys() // Make it an iterator
+                .map(|e| e.as_str())
+                .collect::<std::collections::BTreeSet<&str>>() // Sort it
+                .into_iter().collect::<Vec<&str>>() // Make it into an array
+                .join(" | ")); // Make it into a string
         },
-        &&"fn" => {
-            return FUNCTIONS
-                .lock() // Get the array from the mutex guard
-                .unwrap()
-                .clone()
-                .into_iter() // Make it an iterator
-                .map(|(e, v, _)| e) // Get the key from the tuple
-                .collect::<Vec<String>>() // Make it into an array
-                .join(" | "); // Make it into a string
+        "fn" => {
+            return CommandResult::ok(term
+                .functions()
+                .keys() // Make it an iterator
+                .map(|e| e.as_str())
+                .collect::<std::collections::BTreeSet<&str>>() // Sort it
+                .into_iter().collect::<Vec<&str>>() // Make it into an array
+                .join(" | ")); // Make it into a string
         },
-        &&"cmd" => {
-            return COMMANDS
-                .clone()
-                .into_iter() // Make it an iterator
-                .map(|(e, _, __)| e) // Get the key from the tuple
+        "cmd" => {
+            return CommandResult::ok(COMMANDS
+                .iter() // Make it an iterator
+                .map(|(e, _, __)| *e) // Get the key from the tuple
                 .collect::<Vec<&str>>() // Make it into an array
-                .join(" | "); // Make it into a string
+                .join(" | ")); // Make it into a string
         },
-        _ => return String::from("Couldn't list that. Type |help list| for further info.")
+        _ => return CommandResult::err("Couldn't list that. Type |help list| for further info.")
     };
 }
 
-// Replace things in strings
-pub fn _replace(input:Vec<&str>) -> String {
-    let a = eval_string(input.get(0..input.len()-2).unwrap_or_default().join(" "));
+// List previous input
+pub fn _history(term:&mut Terminal, input:&[Node]) -> CommandResult {
+
+    /*- How many entries to show, all of them by default -*/
+    let count = match input.first() {
+        Some(count) => match term.expand(count).parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => return CommandResult::err("Invalid history count! Type |help history| for further info.")
+        },
+        None => usize::MAX
+    };
 
+    /*- Number the entries like a shell would -*/
+    let history = term.history();
+    let start = history.len().saturating_sub(count);
+    CommandResult::ok(history[start..]
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| format!("{} {entry}", start + index + 1))
+        .collect::<Vec<String>>()
+        .join(&term.config().line_break))
+}
 
-    let (string, replace, with) = (
-        a,
-        input.get(input.len()-2),
-        input.get(input.len()-1),
+// Replace things in strings
+pub fn _replace(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    let split = input.len().saturating_sub(2);
+
+    /*- The string to replace in can also be piped in -*/
+    let string = match (split, term.take_stdin()) {
+        (0, Some(stdin)) => stdin,
+        _ => input[..split].join(" ")
+    };
+
+    let (replace, with) = (
+        input.get(split),
+        input.get(split + 1),
     );
 
     /*- Check the availability of all params -*/
-    // let string = match string { Some(s) => s, None => return String::from("String to replace not specified. Type |help replace| for more info.") };
-    let replace = match replace { Some(s) => s, None => return String::from("Character to replace not specified. Type |help replace| for more info.") };
-    let with = match with { Some(s) => s, None => return String::from("What to replace not specified. Type |help replace| for more info.") };
+    // let string = match string { Some(s) => s, None => return CommandResult::err("String to replace not specified. Type |help replace| for more info.") };
+    let replace = match replace { Some(s) => s, None => return CommandResult::err("Character to replace not specified. Type |help replace| for more info.") };
+    let with = match with { Some(s) => s, None => return CommandResult::err("What to replace not specified. Type |help replace| for more info.") };
 
     /*- Return -*/
-    if with == &":nothing:" {
-        if replace == &":space:" {
+    CommandResult::ok(if with == ":nothing:" {
+        if replace == ":space:" {
             string.replace(" ", "")
         }else {
             string.replace(replace, "")
         }
     }else {
-        if replace == &":space:" {
+        if replace == ":space:" {
             string.replace(" ", with)
         }else {
             string.replace(replace, with)
         }
-    }
+    })
 }
 
 // Random number generator
-pub fn _random(input:Vec<&str>) -> String {
+pub fn _random(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
     let (min, max) = (
-        input.get(0),
+        input.first(),
         input.get(1),
     );
 
     /*- Check the availability of all params -*/
-    let min = match min { Some(s) => s.parse::<i32>().unwrap_or(0i32), None => return String::from("Minimum val not specified.") };
-    let max = match max { Some(s) => s.parse::<i32>().unwrap_or(0i32), None => return String::from("Maximum val not specified.") };
+    let min = match min { Some(s) => s.parse::<i32>().unwrap_or(0i32), None => return CommandResult::err("Minimum val not specified.") };
+    let max = match max { Some(s) => s.parse::<i32>().unwrap_or(0i32), None => return CommandResult::err("Maximum val not specified.") };
 
     /*- Return -*/
-    ((js_sys::Math::random() * (max - min)as f64 + min as f64) as i32).to_string()
+    CommandResult::ok(((term.random() * (max - min)as f64 + min as f64) as i32).to_string())
 }
 
 // Calculate numbers
-pub fn _calc(input:Vec<&str>) -> String {
-    let input:String = eval_string(input.join(" ")).replace(" ", "");
-
-    /*- All regexes -*/
-    let power_re =          Regex::new(r"([0-9\.]+)!([0-9\.]+)").unwrap();
-    let multiplication_re = Regex::new(r"([0-9\.]+)\*([0-9\.]+)").unwrap();
-    let divide_re =         Regex::new(r"([0-9\.]+)/([0-9\.]+)").unwrap();
-    let addition_re =       Regex::new(r"([0-9\.]+)\+([0-9\.]+)").unwrap();
-    let subtraction_re =    Regex::new(r"([0-9\.]+)\-([0-9\.]+)").unwrap();
-
-    /*- Replace power -*/
-    let input = power_re.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")})
-        );
+pub fn _calc(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let mut input = term.expand_all(input);
+
+    /*- --int does exact integer math, --frac exact fractions,
+        and --dec shows the fraction as a decimal number -*/
+    let mut flags = Vec::new();
+    while input.first().is_some_and(|e| ["--int", "--frac", "--dec"].contains(&e.as_str())) {
+        flags.push(input.remove(0));
+    };
+    let flag = |name:&str| flags.iter().any(|e| e == name);
+    if flag("--int") && flag("--frac") { return CommandResult::err("--int and --frac can't be used together!") };
 
-        pow(n1 as f64, n2 as f64).to_string()
-    }).to_string();
+    let input:String = match (input.is_empty(), term.take_stdin()) {
+        (true, Some(stdin)) => stdin,
+        _ => input.join(" ")
+    };
 
-    /*- Replace multiplications -*/
-    let input = multiplication_re.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")})
-        );
+    if input.trim().is_empty() { return CommandResult::err("Calculation not specified! Type |help calc| for further info.") };
 
-        (n1*n2).to_string()
-    }).to_string();
+    let (target, expr) = match calc::parse(&input) {
+        Ok(parsed) => parsed,
+        Err(error) => return CommandResult::err(format!("Error parsing calculation: {error}"))
+    };
 
-    /*- Replace divisions -*/
-    let input = divide_re.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")})
-        );
+    let result = if flag("--int") {
+        expr.evaluate::<BigInt>(term.variables()).map(|e| e.to_string())
+    } else if flag("--frac") {
+        expr.evaluate::<BigRational>(term.variables()).map(|e| match flag("--dec") {
+            true => calc::decimal(&e),
+            false => e.to_string()
+        })
+    } else {
+        /*- Whole numbers stay exact, so 0x7ffffffffffffffe & 1 or 2^63
+            don't lose digits. Anything else, like 7 / 2 or a number too
+            big for a float, is worked out with floats -*/
+        match expr.evaluate::<calc::Whole>(term.variables()) {
+            Ok(number) => Ok(number.to_string()),
+            Err(_) => expr.evaluate::<f64>(term.variables()).map(calc::format)
+        }
+    };
+    let result = match result {
+        Ok(number) => number,
+        Err(error) => return CommandResult::err(format!("Error parsing calculation: {error}"))
+    };
 
-        (n1/n2).to_string()
-    }).to_string();
+    /*- 'ans' is always the last result, 'name = ...' also stores it in name -*/
+    if let Some(name) = target {
+        term.set_variable(name, result.clone());
+    };
+    term.set_variable(String::from("ans"), result.clone());
 
-    /*- Replace additions -*/
-    let input = addition_re.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")})
-        );
+    /*- Return -*/
+    CommandResult::ok(result)
+}
 
-        (n1+n2).to_string()
-    }).to_string();
+// Convert between bases
+pub fn _base(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
 
-    /*- Replace subtractions -*/
-    let input = subtraction_re.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing calculation")})
-        );
+    /*- Check the availability of all params -*/
+    let number = match input.first() { Some(s) => s, None => return CommandResult::err("Number not specified! Type |help base| for further info.") };
+    let radix = match input.get(1).map(|e| e.parse::<u32>()) {
+        Some(Ok(radix)) if (2..=36).contains(&radix) => radix,
+        Some(_) => return CommandResult::err("Base has to be a number from 2 to 36!"),
+        None => return CommandResult::err("Base not specified! Type |help base| for further info.")
+    };
 
-        (n1-n2).to_string()
-    }).to_string();
+    /*- The number can be any whole number calculation, like 0xff or 2^64 -*/
+    match calc::evaluate::<BigInt>(number, term.variables()) {
+        Ok(number) => CommandResult::ok(number.to_str_radix(radix)),
+        Err(error) => CommandResult::err(format!("Error parsing number: {error}"))
+    }
+}
 
-    /*- Return -*/
-    input
+// Convert between units
+pub fn _convert(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+
+    /*- Looks like: convert <amount> <unit> to <unit> -*/
+    let to = match input.iter().rposition(|e| e == "to") {
+        Some(to) if to >= 2 && to + 2 == input.len() => to,
+        _ => return CommandResult::err("Units not specified! Type |help convert| for further info.")
+    };
+    let (amount, from, target) = (input[..to - 1].join(" "), &input[to - 1], &input[to + 1]);
+
+    let amount = match calc::evaluate::<f64>(&amount, term.variables()) {
+        Ok(amount) => amount,
+        Err(error) => return CommandResult::err(format!("Error parsing number: {error}"))
+    };
+
+    match units::convert(amount, from, target) {
+        Ok(value) => CommandResult::ok(format!("{} {target}", calc::format(value))),
+        Err(error) => CommandResult::err(error)
+    }
 }
 
-// Create if-statements
-pub fn _if(input:Vec<&str>) -> String {
-    let input = input.join(" ");
+// Plot a calculation in x
+pub fn _plot(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let mut input = term.expand_all(input);
+
+    /*- --width and --height can go anywhere -*/
+    let (mut width, mut height) = (plot::DEFAULT_WIDTH, plot::DEFAULT_HEIGHT);
+    while let Some(flag) = input.iter().position(|e| e == "--width" || e == "--height") {
+        let value = input.get(flag + 1).and_then(|e| e.parse::<usize>().ok());
+        match (input[flag].as_str(), value) {
+            ("--width", Some(value)) if (10..=200).contains(&value) => width = value,
+            ("--width", _) => return CommandResult::err("Width has to be a number from 10 to 200!"),
+            (_, Some(value)) if (5..=100).contains(&value) => height = value,
+            _ => return CommandResult::err("Height has to be a number from 5 to 100!")
+        };
+        input.drain(flag..=flag + 1);
+    };
 
-    /*- Get the condition -*/
-    let condition_re:Regex = Regex::new(r"\((.+)\) \{(.+)\} else \{(.+)\}").unwrap();
-    condition_re.replace(&input, "");
-    
-    let (condition, _do, _else) = match condition_re.captures(&input) {
-        Some(s) => (
-            match s.get(1) { Some(e) => e.as_str().to_string(), None => return String::from("Couldn't regex that."), },
-            match s.get(2) { Some(e) => e.as_str().to_string(), None => return String::from("Nothing to do if condition succeeds."), },
-            match s.get(3) { Some(e) => e.as_str().to_string(), None => return String::from("ELse shit."), },
-        ),
-        None => return String::from("Condition not found")
+    /*- The range is the last two arguments, if they are both numbers -*/
+    let (mut from, mut to) = (-10.0, 10.0);
+    if input.len() > 2 {
+        let range = &input[input.len() - 2..];
+        if let (Ok(start), Ok(end)) = (calc::evaluate::<f64>(&range[0], term.variables()), calc::evaluate::<f64>(&range[1], term.variables())) {
+            (from, to) = (start, end);
+            input.truncate(input.len() - 2);
+        };
+    };
+    if from >= to {
+        return CommandResult::err("The start of the range has to be below the end!");
     };
 
-    /*- Get if the condition is true / false -*/
-    let condition:bool = match parse_condition(condition) {
-        Ok(s) => s,
-        Err(_) => return String::from("Error parsing condition")
+    let expr = match input.join(" ") {
+        expr if expr.trim().is_empty() => return CommandResult::err("Calculation not specified! Type |help plot| for further info."),
+        expr => match calc::parse(&expr) {
+            Ok((None, expr)) => expr,
+            Ok((Some(_), _)) => return CommandResult::err("Can't plot an assignment!"),
+            Err(error) => return CommandResult::err(format!("Error parsing calculation: {error}"))
+        }
     };
 
-    /*- Execute the command -*/
-    if condition {
-        command(_do)
-    }else {
-        command(_else)
+    match plot::plot(&expr, term.variables(), from, to, width, height, &term.config().line_break) {
+        Ok(graph) => CommandResult::ok(graph),
+        Err(error) => CommandResult::err(format!("Error parsing calculation: {error}"))
     }
 }
 
+/*- Numbers for the statistics commands, from the arguments or
+    what was piped in. Also gives back if --sample was used -*/
+fn stat_numbers(term:&mut Terminal, mut input:Vec<String>, command:&str) -> Result<(Vec<f64>, bool), CommandResult> {
+    let sample = input.first().is_some_and(|e| e == "--sample");
+    if sample { input.remove(0); };
 
-// Helper functions
-fn variable(variable_name:&str) -> String {
+    let text = match (input.is_empty(), term.take_stdin()) {
+        (true, Some(stdin)) => stdin.replace(&term.config().line_break, " "),
+        _ => input.join(" ")
+    };
 
-    /*- Get the variable -*/
-    for (k, v) in VARIABLES.lock().unwrap().clone() {
-        if &&k == &variable_name {
-            return v;
-        };
+    let numbers = stats::parse(&text).map_err(CommandResult::err)?;
+    if numbers.is_empty() {
+        return Err(CommandResult::err(format!("Numbers not specified! Type |help {command}| for further info.")));
     };
 
-    /*- Return else -*/
-    String::from("null")
+    Ok((numbers, sample))
+}
+
+// Statistics
+pub fn _stats(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    let (numbers, sample) = match stat_numbers(term, input, "stats") { Ok(n) => n, Err(error) => return error };
+
+    match stats::summary(&numbers, sample, &term.config().line_break) {
+        Ok(summary) => CommandResult::ok(summary),
+        Err(error) => CommandResult::err(error)
+    }
+}
+pub fn _sum(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "sum") {
+        Ok((numbers, _)) => CommandResult::ok(calc::format(stats::sum(&numbers))),
+        Err(error) => error
+    }
+}
+pub fn _mean(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "mean") {
+        Ok((numbers, _)) => CommandResult::ok(calc::format(stats::mean(&numbers))),
+        Err(error) => error
+    }
+}
+pub fn _median(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "median") {
+        Ok((numbers, _)) => CommandResult::ok(calc::format(stats::median(&numbers))),
+        Err(error) => error
+    }
 }
+pub fn _mode(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "mode") {
+        Ok((numbers, _)) => CommandResult::ok(stats::modes(&numbers).into_iter().map(calc::format).collect::<Vec<String>>().join(", ")),
+        Err(error) => error
+    }
+}
+pub fn _variance(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "variance").map(|(numbers, sample)| stats::variance(&numbers, sample)) {
+        Ok(Ok(variance)) => CommandResult::ok(calc::format(variance)),
+        Ok(Err(error)) => CommandResult::err(error),
+        Err(error) => error
+    }
+}
+pub fn _stddev(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "stddev").map(|(numbers, sample)| stats::stddev(&numbers, sample)) {
+        Ok(Ok(stddev)) => CommandResult::ok(calc::format(stddev)),
+        Ok(Err(error)) => CommandResult::err(error),
+        Err(error) => error
+    }
+}
+pub fn _percentile(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let mut input = term.expand_all(input);
+
+    /*- The first argument is the percentage -*/
+    if input.is_empty() { return CommandResult::err("Percentile not specified! Type |help percentile| for further info.") };
+    let percent = match calc::parse_value::<f64>(&input.remove(0)) {
+        Ok(percent) => percent,
+        Err(error) => return CommandResult::err(error)
+    };
 
-fn parse_num(input:String) -> f32 {
-    input.parse::<f32>().unwrap_or(0f32)
+    match stat_numbers(term, input, "percentile").map(|(numbers, _)| stats::percentile(&numbers, percent)) {
+        Ok(Ok(value)) => CommandResult::ok(calc::format(value)),
+        Ok(Err(error)) => CommandResult::err(error),
+        Err(error) => error
+    }
+}
+pub fn _minmax(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    let input = term.expand_all(input);
+    match stat_numbers(term, input, "minmax") {
+        Ok((numbers, _)) => CommandResult::ok(format!(
+            "{:<9}{}{}{:<9}{}",
+            "min", calc::format(stats::min(&numbers)),
+            term.config().line_break,
+            "max", calc::format(stats::max(&numbers)),
+        )),
+        Err(error) => error
+    }
 }
 
-fn parse_condition(input:String) -> Result<bool, ParseBoolError> {
-    let input:String = eval_string(input).replace(" ", "");
+// Create if-statements
+pub fn _if(term:&mut Terminal, input:&[Node]) -> CommandResult {
+
+    /*- An if-statement looks like this: (condition) {do} else if (condition) {do} else {else}
+        with any number of 'else if' arms, and the 'else' can be left out -*/
+    let mut arms:Vec<(Option<&Node>, &Node)> = Vec::new();
+    let mut rest = input;
+    loop {
+        match rest {
+            [condition, Node::Block(_do), tail @ ..] => {
+                arms.push((Some(condition), _do));
+                rest = tail;
+            },
+            _ => return CommandResult::err("Condition not found")
+        };
 
-    /*- All regexes -*/
-    let bigger_than =   Regex::new(r"([0-9\.]+)>([0-9\.]+)").unwrap();
-    let less_than =     Regex::new(r"([0-9\.]+)<([0-9\.]+)").unwrap();
-    let modulo =        Regex::new(r"([0-9\.]+)%([0-9\.]+)").unwrap();
-    let equals =        Regex::new(r"(.+)==(.+)").unwrap();
+        rest = match rest {
+            [] => break,
+            [_else, Node::Block(_else_do)] if keyword(_else, "else") => {
+                arms.push((None, _else_do));
+                break;
+            },
+            [_else, _if, tail @ ..] if keyword(_else, "else") && keyword(_if, "if") => tail,
+            _ => return CommandResult::err("Expected |else {...}| or |else if (...) {...}| after the block")
+        };
+    };
 
-    /*- Replace modulo -*/
-    let input = modulo.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")})
-        );
+    /*- Execute the first arm whose condition is true -*/
+    for (condition, _do) in arms {
+        let condition = match condition.map(|e| parse_condition(term, e)) {
+            Some(Ok(condition)) => condition,
+            Some(Err(error)) => return error,
+            None => true
+        };
 
-        (n1 % n2).to_string()
-    });
+        if condition { return term.run_node(_do); };
+    };
 
-    /*- Replace bigger_than -*/
-    let input = bigger_than.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")})
-        );
+    CommandResult::default()
+}
 
-        (n1 > n2).to_string()
-    });
+// Loop while a condition is true
+pub fn _while(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    run_loop(term, input, true, "while")
+}
 
-    /*- Replace less than -*/
-    let input = less_than.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            parse_num(match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")}),
-            parse_num(match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing number.")})
-        );
+// Loop until a condition is true
+pub fn _until(term:&mut Terminal, input:&[Node]) -> CommandResult {
+    run_loop(term, input, false, "until")
+}
 
-        (n1 < n2).to_string()
-    });
+/*- Helper functions -*/
+/*- Work out a condition like (a > 1 && b < 5), which
+    has to be wrapped in parentheses -*/
+fn parse_condition(term:&mut Terminal, condition:&Node) -> Result<bool, CommandResult> {
+    let condition = match term.expand(condition).trim().strip_prefix('(').and_then(|e| e.strip_suffix(')')) {
+        Some(e) => e.to_string(),
+        None => return Err(CommandResult::err("Condition not found"))
+    };
 
-    /*- Replace equals -*/
-    let input = equals.replace_all(&input, |caps: &Captures| {
-        let (n1, n2) = (
-            match caps.get(1) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing string.")},
-            match caps.get(2) { Some(s) => s.as_str().to_string(), None => return String::from("Error parsing string.")}
-        );
+    condition::evaluate(&condition, term.variables())
+        .map_err(|error| CommandResult::err(format!("Error parsing condition: {error}")))
+}
 
-        (n1 == n2).to_string()
-    });
+/*- A loop looks like this: (condition) {do}. It keeps
+    going as long as the condition is 'expected' -*/
+fn run_loop(term:&mut Terminal, input:&[Node], expected:bool, command:&str) -> CommandResult {
+    let (condition, _do) = match input {
+        [condition, Node::Block(_do)] => (condition, _do),
+        _ => return CommandResult::err(format!("Condition not found! Type |help {command}| for further info."))
+    };
 
-    /*- Return -*/
-    input.parse::<bool>()
+    /*- The output of all runs -*/
+    let mut output = CommandResult::default();
+
+    /*- Checking the condition uses up a step too, so even
+        a loop that runs nothing stops at the step limit -*/
+    while term.step() {
+        match parse_condition(term, condition) {
+            Ok(condition) if condition == expected => (),
+            Ok(_) => break,
+            Err(error) => {
+                term.append(&mut output, error);
+                break;
+            }
+        };
+
+        let result = term.run_node(_do);
+        term.append(&mut output, result);
+    };
+
+    output
+}
+
+/*- Words like 'else' that are part of a command -*/
+fn keyword(node:&Node, word:&str) -> bool {
+    matches!(node, Node::Literal(text) if text == word)
 }
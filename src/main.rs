@@ -0,0 +1,42 @@
+/*- Imports -*/
+use std::io::{self, BufRead, IsTerminal, Write};
+use wasm_terminal::{Config, Terminal};
+
+/*- A REPL that reads commands from stdin, one per line -*/
+fn main() {
+    let mut terminal = Terminal::with_config(Config {
+        line_break: String::from("\n"),
+        ..Default::default()
+    });
+
+    /*- Only show a prompt when someone is typing -*/
+    let interactive = io::stdin().is_terminal();
+    let mut status = 0;
+
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush().ok();
+        };
+
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(error) => {
+                eprintln!("{error}");
+                break;
+            }
+        };
+
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() { continue; };
+
+        let result = terminal.run(line.to_string());
+        if !result.output_str().is_empty() { println!("{}", result.output_str()); };
+        if !result.error_str().is_empty() { eprintln!("{}", result.error_str()); };
+        status = result.status();
+    };
+
+    std::process::exit(status);
+}
@@ -0,0 +1,587 @@
+/*- Imports -*/
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /*- ':random 1-10:' is kept together as one word -*/
+    static ref RANDOM_PLACEHOLDER:Regex = Regex::new(r"^:random\s[0-9]+-[0-9]+:").unwrap();
+}
+
+/*- Tokens produced by the lexer -*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// A word, made out of plain text, quoted text and substitutions
+    /// like `eval(...)` and `var(...)`
+    Word(Vec<WordPart>),
+
+    /// `&&`, `||` or `;` between commands
+    Operator(Operator),
+
+    /// `|` - output of the left command becomes input for the right one
+    Pipe,
+
+    /// `{` - start of a block
+    OpenBrace,
+
+    /// `}` - end of a block
+    CloseBrace,
+}
+
+/*- What decides if the next command in a sequence runs -*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    /// `&&` - only if the previous command succeeded
+    And,
+
+    /// `||` - only if the previous command failed
+    Or,
+
+    /// `;` - always
+    Then,
+}
+
+impl Operator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Then => ";",
+        }
+    }
+}
+
+/*- The pieces a word can be made out of -*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    Text(String),
+    Verbatim(String),
+    Substitution(Vec<Token>),
+    Call(String, Vec<Vec<WordPart>>),
+}
+
+/*- The parsed command tree -*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Commands separated by operators. The operator
+    /// before the first command is always `Then`
+    Sequence(Vec<(Operator, Node)>),
+
+    /// Commands separated by `|`
+    Pipeline(Vec<Node>),
+
+    /// A command name followed by its arguments
+    Command(Vec<Node>),
+
+    /// Commands wrapped in `{` and `}`, which are not run
+    /// until the command receiving them decides to
+    Block(Box<Node>),
+
+    /// `eval(...)` - replaced with the output of the commands inside
+    Substitution(Box<Node>),
+
+    /// `var(...)` or `replace(...)` - replaced with the
+    /// result of the function, given the arguments
+    Call(String, Vec<Node>),
+
+    /// Plain text, where things like `<variable>` get replaced
+    Literal(String),
+
+    /// Text from single quotes or backslash escapes, which is kept as-is
+    Verbatim(String),
+
+    /// Several parts glued together without whitespace,
+    /// like `index-eval(calc 1 + 1)`
+    Word(Vec<Node>),
+}
+
+impl Node {
+    /// Replace text in every literal of the tree. Used for things
+    /// like the `#i` index in |repeat| and `--param` in functions
+    pub fn replace(&self, from:&str, to:&str) -> Node {
+        match self {
+            Node::Sequence(nodes) => Node::Sequence(nodes.iter().map(|(operator, e)| (*operator, e.replace(from, to))).collect()),
+            Node::Pipeline(nodes) => Node::Pipeline(nodes.iter().map(|e| e.replace(from, to)).collect()),
+            Node::Command(nodes) => Node::Command(nodes.iter().map(|e| e.replace(from, to)).collect()),
+            Node::Word(nodes) => Node::Word(nodes.iter().map(|e| e.replace(from, to)).collect()),
+            Node::Block(node) => Node::Block(Box::new(node.replace(from, to))),
+            Node::Substitution(node) => Node::Substitution(Box::new(node.replace(from, to))),
+            Node::Call(name, nodes) => Node::Call(name.clone(), nodes.iter().map(|e| e.replace(from, to)).collect()),
+            Node::Literal(text) => Node::Literal(text.replace(from, to)),
+            Node::Verbatim(text) => Node::Verbatim(text.clone()),
+        }
+    }
+}
+
+/*- Functions that are replaced with their result, like 'var(name)' -*/
+pub const CALLS:&[&str] = &["var", "replace"];
+
+/*- How deep blocks and substitutions can be nested. Parsing and
+    running them is recursive, and the stack in wasm is small -*/
+const MAX_NESTING:usize = 100;
+
+/*- Where a word is being lexed, which decides what ends it -*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// At the top level, or inside a block
+    Command,
+
+    /// Inside `eval(...)`, where the closing `)` ends it
+    Substitution,
+
+    /// An argument of something like `replace(...)`, ended by `,` or `)`
+    Argument,
+}
+
+/*- Lexer -*/
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+
+    /*- Substitutions the lexer is inside of -*/
+    depth: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, pattern:&str) -> bool {
+        pattern.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    /*- Lex tokens until the input ends, or until the
+        closing paren of an 'eval(' in substitution mode -*/
+    fn tokens(&mut self, mode:Mode) -> Result<Vec<Token>, String> {
+        let mut tokens = Vec::new();
+
+        loop {
+            /*- Skip whitespace -*/
+            while self.peek().is_some_and(char::is_whitespace) { self.pos += 1; };
+
+            match self.peek() {
+                None => return Ok(tokens),
+                Some(')') if mode == Mode::Substitution => return Ok(tokens),
+                Some('{') => {
+                    self.pos += 1;
+                    tokens.push(Token::OpenBrace);
+                },
+                Some('}') => {
+                    self.pos += 1;
+                    tokens.push(Token::CloseBrace);
+                },
+                Some(_) if self.starts_with("&&") => {
+                    self.pos += 2;
+                    tokens.push(Token::Operator(Operator::And));
+                },
+                Some(_) if self.starts_with("||") => {
+                    self.pos += 2;
+                    tokens.push(Token::Operator(Operator::Or));
+                },
+                Some(';') => {
+                    self.pos += 1;
+                    tokens.push(Token::Operator(Operator::Then));
+                },
+                Some('|') => {
+                    self.pos += 1;
+                    tokens.push(Token::Pipe);
+                },
+                Some(_) => tokens.push(Token::Word(self.word(mode)?)),
+            };
+        };
+    }
+
+    /*- Lex a single word. A word that starts with '(' goes on to its
+        ')', whitespace included, so |if (a == b)| gets one word for the
+        condition. At the top level, a '(' without a ')' is just text,
+        like any other parenthesis outside of a substitution. Inside of
+        one, parentheses always have to be balanced, so there's nothing
+        to try again -*/
+    fn word(&mut self, mode:Mode) -> Result<Vec<WordPart>, String> {
+        let start = self.pos;
+        if mode == Mode::Command && self.peek() == Some('(') {
+            match self.word_parts(mode, true) {
+                Ok(parts) => return Ok(parts),
+                Err(_) => self.pos = start
+            };
+        };
+
+        self.word_parts(mode, false)
+    }
+
+    fn word_parts(&mut self, mode:Mode, group:bool) -> Result<Vec<WordPart>, String> {
+        let mut parts:Vec<WordPart> = Vec::new();
+        let mut text = String::new();
+
+        /*- Positions of the '(' of the group that haven't been closed yet -*/
+        let mut open:Vec<usize> = Vec::new();
+        if group {
+            open.push(self.pos);
+            text.push('(');
+            self.pos += 1;
+        };
+
+        while let Some(c) = self.peek() {
+            /*- Things that end a word -*/
+            if open.is_empty() {
+                let end = match mode {
+                    Mode::Argument => c == ',' || c == ')',
+                    _ => c.is_whitespace()
+                        || c == '}'
+                        || c == ';'
+                        || c == '|'
+                        || (c == ')' && mode == Mode::Substitution)
+                        || self.starts_with("&&")
+                };
+                if end { break; };
+            };
+
+            /*- 'eval(' and 'var(' start a substitution, unless they're the end of a longer name -*/
+            if self.substitution(&mut parts, &mut text)? { continue; };
+
+            match c {
+                /*- Single quotes keep everything as-is -*/
+                '\'' => {
+                    flush(&mut parts, &mut text);
+                    self.pos += 1;
+
+                    let mut quoted = String::new();
+                    loop {
+                        match self.peek() {
+                            Some('\'') => break,
+                            Some(c) => quoted.push(c),
+                            None => return Err(String::from("Missing closing ' quote"))
+                        };
+                        self.pos += 1;
+                    };
+
+                    self.pos += 1;
+                    parts.push(WordPart::Verbatim(quoted));
+                    continue;
+                },
+
+                /*- Double quotes still allow things like <variable> and eval(...) -*/
+                '"' => {
+                    flush(&mut parts, &mut text);
+                    self.pos += 1;
+
+                    /*- So that "" still becomes an (empty) argument -*/
+                    parts.push(WordPart::Verbatim(String::new()));
+                    loop {
+                        match self.peek() {
+                            Some('"') => break,
+                            Some('\\') => self.escape(&mut parts, &mut text),
+                            Some(c) => {
+                                if self.substitution(&mut parts, &mut text)? { continue; };
+                                text.push(c);
+                                self.pos += 1;
+                            },
+                            None => return Err(String::from("Missing closing \" quote"))
+                        };
+                    };
+
+                    self.pos += 1;
+                    flush(&mut parts, &mut text);
+                    continue;
+                },
+                '\\' => {
+                    self.escape(&mut parts, &mut text);
+                    continue;
+                },
+                _ => ()
+            };
+
+            /*- ':random 1-10:' contains a space, but is still one word -*/
+            if let Some(length) = self.random_placeholder() {
+                text.extend(&self.chars[self.pos..self.pos + length]);
+                self.pos += length;
+                continue;
+            };
+
+            /*- Inside substitutions every parenthesis has to be balanced.
+                At the top level they only count inside the group -*/
+            if mode != Mode::Command || !open.is_empty() {
+                match c {
+                    '(' => open.push(self.pos),
+                    ')' => { open.pop(); },
+                    _ => ()
+                };
+            };
+            text.push(c);
+            self.pos += 1;
+        };
+
+        /*- The group needs its ')', else 'word' lexes it again as text -*/
+        if let Some(position) = open.last() {
+            return Err(format!("Missing ')' for the '(' at column {}", position + 1));
+        };
+
+        flush(&mut parts, &mut text);
+        Ok(parts)
+    }
+
+    /*- Lex an 'eval(...)', 'var(...)' or 'replace(...)' substitution
+        if there is one at the current position. They can be nested
+        in each other as deep as needed -*/
+    fn substitution(&mut self, parts:&mut Vec<WordPart>, text:&mut String) -> Result<bool, String> {
+        if self.follows_identifier() { return Ok(false) };
+        let start = self.pos;
+
+        /*- eval(command) -*/
+        if self.starts_with("eval(") {
+            flush(parts, text);
+            self.pos += 5;
+
+            self.enter(start)?;
+            let tokens = self.tokens(Mode::Substitution);
+            self.depth -= 1;
+
+            let tokens = tokens?;
+            self.close(start, "eval")?;
+            parts.push(WordPart::Substitution(tokens));
+            return Ok(true);
+        };
+
+        /*- name(argument,argument) -*/
+        for name in CALLS {
+            if !self.starts_with(&format!("{name}(")) { continue; };
+            flush(parts, text);
+            self.pos += name.len() + 1;
+
+            self.enter(start)?;
+            let arguments = self.arguments();
+            self.depth -= 1;
+
+            let arguments = arguments?;
+            self.close(start, name)?;
+            parts.push(WordPart::Call(name.to_string(), arguments));
+            return Ok(true);
+        };
+
+        Ok(false)
+    }
+
+    /*- Comma separated arguments, up to the closing ')' -*/
+    fn arguments(&mut self) -> Result<Vec<Vec<WordPart>>, String> {
+        let mut arguments = vec![self.word(Mode::Argument)?];
+        while self.peek() == Some(',') {
+            self.pos += 1;
+            arguments.push(self.word(Mode::Argument)?);
+        };
+        Ok(arguments)
+    }
+
+    /*- Go into the substitution that started at 'start'. 'word' can
+        lex a word again after an error, so leaving it has to happen
+        even when there is one -*/
+    fn enter(&mut self, start:usize) -> Result<(), String> {
+        if self.depth == MAX_NESTING { return Err(format!("Nested too deep at column {}", start + 1)) };
+        self.depth += 1;
+        Ok(())
+    }
+
+    /*- Consume the ')' of a substitution that started at 'start' -*/
+    fn close(&mut self, start:usize, name:&str) -> Result<(), String> {
+        match self.peek() {
+            Some(')') => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => Err(format!("Missing ')' for the '{name}(' at column {}", start + 1))
+        }
+    }
+
+    /*- Backslash escapes. '\n' is a line break, anything
+        else is the character itself, kept as-is -*/
+    fn escape(&mut self, parts:&mut Vec<WordPart>, text:&mut String) {
+        self.pos += 1;
+
+        let escaped = match self.peek() {
+            Some('n') => String::from("\n"),
+            Some('t') => String::from("\t"),
+            Some(c) => c.to_string(),
+
+            /*- A trailing backslash is just a backslash -*/
+            None => {
+                text.push('\\');
+                return;
+            }
+        };
+
+        self.pos += 1;
+        flush(parts, text);
+        parts.push(WordPart::Verbatim(escaped));
+    }
+
+    fn follows_identifier(&self) -> bool {
+        self.pos > 0 && self.chars.get(self.pos - 1).is_some_and(|c| c.is_alphanumeric() || *c == '_')
+    }
+
+    /*- Length of a ':random min-max:' placeholder at the current position -*/
+    fn random_placeholder(&self) -> Option<usize> {
+        if !self.starts_with(":random") { return None };
+
+        let rest:String = self.chars[self.pos..].iter().collect();
+        RANDOM_PLACEHOLDER.find(&rest).map(|m| rest[..m.end()].chars().count())
+    }
+}
+
+/*- Move collected text into the word parts -*/
+fn flush(parts:&mut Vec<WordPart>, text:&mut String) {
+    if !text.is_empty() { parts.push(WordPart::Text(std::mem::take(text))); };
+}
+
+/// Split input into tokens
+pub fn lex(input:&str) -> Result<Vec<Token>, String> {
+    Lexer { chars: input.chars().collect(), pos: 0, depth: 0 }.tokens(Mode::Command)
+}
+
+/*- Parser -*/
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+
+    /*- Blocks and substitutions the parser is inside of -*/
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /*- sequence := pipeline (operator pipeline)* [';'] -*/
+    fn sequence(&mut self) -> Result<Node, String> {
+        let mut commands:Vec<(Operator, Node)> = Vec::new();
+        let mut operator = Operator::Then;
+
+        loop {
+            let command = self.pipeline()?;
+
+            match self.peek() {
+                Some(Token::Operator(next)) => {
+                    let next = *next;
+                    let command = match command {
+                        Some(command) => command,
+                        None => return Err(format!("Expected a command before '{}'", next.symbol()))
+                    };
+                    commands.push((operator, command));
+                    operator = next;
+                    self.pos += 1;
+                },
+                _ => {
+                    if let Some(command) = command {
+                        commands.push((operator, command));
+
+                    /*- Only ';' may end a sequence, like |return a;| -*/
+                    }else if !commands.is_empty() && operator != Operator::Then {
+                        return Err(format!("Expected a command after '{}'", operator.symbol()));
+                    };
+                    break;
+                }
+            };
+        };
+
+        Ok(Node::Sequence(commands))
+    }
+
+    /*- pipeline := command ('|' command)* -*/
+    fn pipeline(&mut self) -> Result<Option<Node>, String> {
+        let mut commands:Vec<Node> = Vec::new();
+
+        loop {
+            let command = self.command()?;
+
+            match self.peek() {
+                Some(Token::Pipe) => {
+                    if command.is_empty() { return Err(String::from("Expected a command before '|'")) };
+                    commands.push(Node::Command(command));
+                    self.pos += 1;
+                },
+                _ => {
+                    if !command.is_empty() {
+                        commands.push(Node::Command(command));
+                    }else if !commands.is_empty() {
+                        return Err(String::from("Expected a command after '|'"));
+                    };
+                    break;
+                }
+            };
+        };
+
+        /*- Most commands aren't piped anywhere -*/
+        Ok(match commands.len() {
+            0 => None,
+            1 => commands.pop(),
+            _ => Some(Node::Pipeline(commands))
+        })
+    }
+
+    /*- command := (word | '{' sequence '}')* -*/
+    fn command(&mut self) -> Result<Vec<Node>, String> {
+        let mut items:Vec<Node> = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(parts)) => {
+                    let parts = parts.clone();
+                    self.pos += 1;
+                    items.push(word(parts, self.depth)?);
+                },
+                Some(Token::OpenBrace) => {
+                    if self.depth == MAX_NESTING { return Err(String::from("Blocks and substitutions are nested too deep")) };
+                    self.pos += 1;
+                    self.depth += 1;
+                    let inner = self.sequence()?;
+                    self.depth -= 1;
+
+                    /*- Blocks need to be closed -*/
+                    match self.peek() {
+                        Some(Token::CloseBrace) => self.pos += 1,
+                        _ => return Err(String::from("Missing '}' to close block"))
+                    };
+                    items.push(Node::Block(Box::new(inner)));
+                },
+                _ => return Ok(items)
+            };
+        };
+    }
+}
+
+/*- Turn the parts of a word token into a node -*/
+fn word(parts:Vec<WordPart>, depth:usize) -> Result<Node, String> {
+    let mut nodes:Vec<Node> = Vec::with_capacity(parts.len());
+
+    for part in parts {
+        nodes.push(match part {
+            WordPart::Text(text) => Node::Literal(text),
+            WordPart::Verbatim(text) => Node::Verbatim(text),
+            WordPart::Substitution(tokens) => Node::Substitution(Box::new(parse_tokens(tokens, depth + 1)?)),
+            WordPart::Call(name, arguments) => Node::Call(
+                name,
+                arguments.into_iter().map(|e| word(e, depth + 1)).collect::<Result<Vec<Node>, String>>()?
+            ),
+        });
+    };
+
+    /*- Most words are just one part -*/
+    if nodes.len() == 1 { return Ok(nodes.remove(0)) };
+    Ok(Node::Word(nodes))
+}
+
+/*- 'depth' is how deep the tokens are nested in blocks and substitutions -*/
+fn parse_tokens(tokens:Vec<Token>, depth:usize) -> Result<Node, String> {
+    if depth > MAX_NESTING { return Err(String::from("Blocks and substitutions are nested too deep")) };
+    let mut parser = Parser { tokens, pos: 0, depth };
+    let node = parser.sequence()?;
+
+    /*- Anything left over is a '}' without a matching '{' -*/
+    if parser.pos < parser.tokens.len() { return Err(String::from("Unexpected '}'")) };
+
+    Ok(node)
+}
+
+/// Parse input into a command tree
+pub fn parse(input:&str) -> Result<Node, String> {
+    parse_tokens(lex(input)?, 0)
+}
@@ -0,0 +1,107 @@
+/*- Imports -*/
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+/// Things the interpreter needs from wherever it's running,
+/// the browser or a native binary
+pub trait Platform: Send {
+    /// A random number in `0..1`
+    fn random(&self) -> f64;
+
+    /// Write a debug message somewhere the user won't see it
+    fn log(&self, message:&str);
+
+    /// Milliseconds since the unix epoch
+    fn now(&self) -> f64;
+}
+
+/*- Browser -*/
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    // Use `js_namespace` here to bind `console.log(..)` instead of just
+    // `log(..)`
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+/// Uses `Math.random`, `console.log` and `Date.now`
+#[cfg(target_arch = "wasm32")]
+pub struct Web;
+
+#[cfg(target_arch = "wasm32")]
+impl Platform for Web {
+    fn random(&self) -> f64 {
+        js_sys::Math::random()
+    }
+
+    fn log(&self, message:&str) {
+        log(message);
+    }
+
+    fn now(&self) -> f64 {
+        js_sys::Date::now()
+    }
+}
+
+/*- Native -*/
+/// Uses a xorshift generator seeded from the clock, and stderr for logs
+pub struct Native {
+    state: Cell<u64>,
+}
+
+impl Native {
+    pub fn new() -> Self {
+        let seed = (Self::epoch_millis() * 1000.0) as u64;
+        Self::with_seed(seed)
+    }
+
+    /// Same seed gives the same random numbers, which is nice for tests
+    pub fn with_seed(seed:u64) -> Self {
+        /*- Xorshift gets stuck on 0 -*/
+        Self { state: Cell::new(seed.max(1)) }
+    }
+
+    fn epoch_millis() -> f64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|e| e.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+}
+
+impl Default for Native {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Platform for Native {
+    fn random(&self) -> f64 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state.set(x);
+
+        /*- Top 53 bits make up the mantissa of a f64 in 0..1 -*/
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn log(&self, message:&str) {
+        eprintln!("{message}");
+    }
+
+    fn now(&self) -> f64 {
+        Self::epoch_millis()
+    }
+}
+
+/// The platform for the target that is being compiled for
+pub fn default_platform() -> Box<dyn Platform> {
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(Web);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(Native::new());
+}
@@ -0,0 +1,95 @@
+/*- Character grid graphs of calculations in x, used by 'plot' -*/
+
+/*- Imports -*/
+use crate::calc::{self, CalcError, Expr};
+use std::collections::HashMap;
+
+pub const DEFAULT_WIDTH:usize = 60;
+pub const DEFAULT_HEIGHT:usize = 20;
+
+/// Draw `expr` for x going from `from` to `to`, as a grid of
+/// `width` by `height` characters with axes and tick labels
+pub fn plot(expr:&Expr, variables:&HashMap<String, String>, from:f64, to:f64, width:usize, height:usize, line_break:&str) -> Result<String, CalcError> {
+    /*- x shadows any variable with the same name -*/
+    let mut variables = variables.clone();
+    let xs:Vec<f64> = (0..width).map(|i| from + (to - from) * i as f64 / (width - 1) as f64).collect();
+
+    /*- Points where the calculation fails, like sqrt(-1), are left out -*/
+    let mut first_error = None;
+    let ys:Vec<Option<f64>> = xs.iter().map(|x| {
+        variables.insert(String::from("x"), x.to_string());
+        match expr.evaluate::<f64>(&variables) {
+            Ok(y) if y.is_finite() => Some(y),
+            Ok(_) => None,
+            Err(error) => {
+                first_error.get_or_insert(error);
+                None
+            }
+        }
+    }).collect();
+
+    let finite:Vec<f64> = ys.iter().flatten().copied().collect();
+    if finite.is_empty() {
+        if let Some(error) = first_error { return Err(error); };
+    };
+
+    /*- A flat line gets some room above and below -*/
+    let (mut bottom, mut top) = (
+        finite.iter().copied().fold(f64::INFINITY, f64::min),
+        finite.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    );
+    if finite.is_empty() { (bottom, top) = (-1.0, 1.0); };
+    if bottom == top { (bottom, top) = (bottom - 1.0, top + 1.0); };
+
+    let row = |y:f64| ((top - y) / (top - bottom) * (height - 1) as f64).round() as usize;
+    let column = |x:f64| ((x - from) / (to - from) * (width - 1) as f64).round() as usize;
+    let mut grid = vec![vec![' '; width]; height];
+
+    /*- Axes through zero, if it's in view -*/
+    let zero_row = (bottom..=top).contains(&0.0).then(|| row(0.0));
+    let zero_column = (from..=to).contains(&0.0).then(|| column(0.0));
+    if let Some(zero_row) = zero_row {
+        grid[zero_row].fill('-');
+    };
+    if let Some(zero_column) = zero_column {
+        for line in grid.iter_mut() {
+            line[zero_column] = if line[zero_column] == '-' { '+' } else { '|' };
+        };
+    };
+
+    for (i, y) in ys.iter().enumerate() {
+        if let Some(y) = y { grid[row(*y)][i] = '*'; };
+    };
+
+    /*- y labels on the top and bottom row, and where y is 0 -*/
+    let mut labels = vec![String::new(); height];
+    if let Some(zero_row) = zero_row { labels[zero_row] = String::from("0"); };
+    labels[0] = label(top);
+    labels[height - 1] = label(bottom);
+    let margin = labels.iter().map(|e| e.chars().count()).max().unwrap_or(0);
+
+    let mut lines:Vec<String> = grid.iter().zip(&labels)
+        .map(|(line, label)| format!("{label:>margin$} |{}", line.iter().collect::<String>()))
+        .collect();
+    lines.push(format!("{:margin$} +{}", "", "-".repeat(width)));
+
+    /*- x labels at the start, the middle and the end -*/
+    let (start, middle, end) = (label(from), label((from + to) / 2.0), label(to));
+    let mut x_labels = vec![' '; width];
+    let mut put = |text:&str, at:usize| {
+        for (i, c) in text.chars().enumerate() {
+            if let Some(slot) = x_labels.get_mut(at + i) { *slot = c; };
+        };
+    };
+    put(&start, 0);
+    put(&middle, (width / 2).saturating_sub(middle.chars().count() / 2));
+    put(&end, width.saturating_sub(end.chars().count()));
+    lines.push(format!("{:margin$}  {}", "", x_labels.iter().collect::<String>()));
+
+    Ok(lines.iter().map(|e| e.trim_end()).collect::<Vec<&str>>().join(line_break))
+}
+
+/*- Tick labels only need a few digits -*/
+fn label(number:f64) -> String {
+    calc::format(format!("{number:.3e}").parse::<f64>().unwrap_or(number))
+}
@@ -0,0 +1,154 @@
+/*- Imports -*/
+use wasm_bindgen::prelude::*;
+
+/*- Status codes, the same ones a unix shell would use -*/
+pub const SUCCESS:i32 = 0;
+pub const FAILURE:i32 = 1;
+pub const SYNTAX_ERROR:i32 = 2;
+pub const NOT_FOUND:i32 = 127;
+
+/// Side effects that the JS-side needs to act on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Clear the terminal screen
+    Clear,
+
+    /// Change the theme
+    Theme(String),
+
+    /// Toggle fullscreen
+    Fullscreen,
+}
+
+impl Event {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Clear => "clear",
+            Event::Theme(_) => "theme",
+            Event::Fullscreen => "fullscreen",
+        }
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Event::Theme(theme) => Some(theme),
+            _ => None
+        }
+    }
+}
+
+/// What running a command gave back
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommandResult {
+    output: String,
+    error: String,
+    status: i32,
+    events: Vec<Event>,
+}
+
+#[wasm_bindgen]
+impl CommandResult {
+    /// Normal output of the command
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        self.output.clone()
+    }
+
+    /// Error text, empty if nothing went wrong
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> String {
+        self.error.clone()
+    }
+
+    /// 0 on success, anything else on failure
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> i32 {
+        self.status
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.status == SUCCESS
+    }
+
+    /// Events as an array of `{ kind, value }` objects
+    #[wasm_bindgen(getter)]
+    pub fn events(&self) -> js_sys::Array {
+        self.events.iter().map(|event| {
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"kind".into(), &event.kind().into()).unwrap();
+            js_sys::Reflect::set(&object, &"value".into(), &event.value().map_or(JsValue::NULL, JsValue::from)).unwrap();
+            JsValue::from(object)
+        }).collect()
+    }
+}
+
+impl CommandResult {
+    pub fn ok(output:impl Into<String>) -> Self {
+        Self { output: output.into(), ..Default::default() }
+    }
+
+    pub fn err(error:impl Into<String>) -> Self {
+        Self { error: error.into(), status: FAILURE, ..Default::default() }
+    }
+
+    pub fn with_status(mut self, status:i32) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_event(mut self, event:Event) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    pub fn output_str(&self) -> &str {
+        &self.output
+    }
+
+    pub fn error_str(&self) -> &str {
+        &self.error
+    }
+
+    pub fn event_list(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn set_output(&mut self, output:String) {
+        self.output = output;
+    }
+
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Cut the output down to at most `max` bytes
+    pub fn truncate_output(&mut self, max:usize) {
+        let mut end = max.min(self.output.len());
+        while !self.output.is_char_boundary(end) { end -= 1; };
+        self.output.truncate(end);
+    }
+
+    /// Put `line_break` where the output and error have newlines
+    pub fn replace_line_breaks(&mut self, line_break:&str) {
+        if line_break == "\n" { return };
+        self.output = self.output.replace('\n', line_break);
+        self.error = self.error.replace('\n', line_break);
+    }
+
+    /// Add another result after this one. Output and errors are put
+    /// on new lines, the status becomes the one of the last result
+    pub fn append(&mut self, other:CommandResult, line_break:&str) {
+        push_line(&mut self.output, other.output, line_break);
+        push_line(&mut self.error, other.error, line_break);
+        self.status = other.status;
+        self.events.extend(other.events);
+    }
+}
+
+fn push_line(into:&mut String, line:String, line_break:&str) {
+    if line.is_empty() { return };
+    if !into.is_empty() { into.push_str(line_break); };
+    into.push_str(&line);
+}
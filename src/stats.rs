@@ -0,0 +1,109 @@
+/*- Statistics over lists of numbers, used by 'stats', 'mean' and friends -*/
+
+/*- Imports -*/
+use crate::calc;
+
+/// Read numbers separated by whitespace or commas, like `1, 2.5 -3`
+pub fn parse(input:&str) -> Result<Vec<f64>, String> {
+    input.split(|c:char| c.is_whitespace() || c == ',')
+        .filter(|e| !e.is_empty())
+        .map(calc::parse_value::<f64>)
+        .collect()
+}
+
+pub fn sum(numbers:&[f64]) -> f64 {
+    numbers.iter().sum()
+}
+
+pub fn mean(numbers:&[f64]) -> f64 {
+    sum(numbers) / numbers.len() as f64
+}
+
+pub fn min(numbers:&[f64]) -> f64 {
+    numbers.iter().copied().fold(f64::INFINITY, f64::min)
+}
+
+pub fn max(numbers:&[f64]) -> f64 {
+    numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+}
+
+fn sorted(numbers:&[f64]) -> Vec<f64> {
+    let mut sorted = numbers.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+pub fn median(numbers:&[f64]) -> f64 {
+    let sorted = sorted(numbers);
+    let middle = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+        _ => sorted[middle]
+    }
+}
+
+/// The most common numbers, smallest first. More than
+/// one if several numbers are equally common
+pub fn modes(numbers:&[f64]) -> Vec<f64> {
+    let mut counts:Vec<(f64, usize)> = Vec::new();
+    for number in sorted(numbers) {
+        match counts.last_mut() {
+            Some((last, count)) if *last == number => *count += 1,
+            _ => counts.push((number, 1))
+        };
+    };
+
+    let most = counts.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    counts.into_iter().filter(|(_, count)| *count == most).map(|(number, _)| number).collect()
+}
+
+/// Population variance, or the sample variance if `sample` is set
+pub fn variance(numbers:&[f64], sample:bool) -> Result<f64, String> {
+    let divisor = match sample {
+        true if numbers.len() < 2 => return Err(String::from("Sample variance needs at least 2 numbers")),
+        true => numbers.len() - 1,
+        false => numbers.len()
+    };
+
+    let mean = mean(numbers);
+    Ok(numbers.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / divisor as f64)
+}
+
+pub fn stddev(numbers:&[f64], sample:bool) -> Result<f64, String> {
+    variance(numbers, sample).map(f64::sqrt)
+}
+
+/// The value below which `percent` percent of the numbers
+/// fall, interpolating between the two closest ones
+pub fn percentile(numbers:&[f64], percent:f64) -> Result<f64, String> {
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("Percentile has to be from 0 to 100, not {percent}"));
+    };
+
+    let sorted = sorted(numbers);
+    let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    Ok(sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64))
+}
+
+/// Everything above as a table, one statistic per line
+pub fn summary(numbers:&[f64], sample:bool, line_break:&str) -> Result<String, String> {
+    let modes = modes(numbers).into_iter().map(calc::format).collect::<Vec<String>>().join(", ");
+    let rows = [
+        ("count", numbers.len().to_string()),
+        ("sum", calc::format(sum(numbers))),
+        ("mean", calc::format(mean(numbers))),
+        ("median", calc::format(median(numbers))),
+        ("mode", modes),
+        ("min", calc::format(min(numbers))),
+        ("max", calc::format(max(numbers))),
+        ("range", calc::format(max(numbers) - min(numbers))),
+        ("variance", calc::format(variance(numbers, sample)?)),
+        ("stddev", calc::format(stddev(numbers, sample)?)),
+    ];
+
+    Ok(rows.iter()
+        .map(|(name, value)| format!("{name:<9}{value}"))
+        .collect::<Vec<String>>()
+        .join(line_break))
+}
@@ -0,0 +1,439 @@
+/*- Imports -*/
+use crate::{parser::{self, Node, Operator}, platform::{self, Platform}, result::{self, CommandResult}, COMMANDS};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/*- Compiled once, these are used for every word of every command -*/
+lazy_static! {
+    /*- Can be called like this - :random 10-124: which will replace the input with something like 23 -*/
+    static ref RANDOM:Regex = Regex::new(r":random\s([0-9]+)-([0-9]+):").unwrap();
+
+    /*- <variable_name> -*/
+    static ref VARIABLE:Regex = Regex::new(r"<([^<>\s]+)>").unwrap();
+}
+
+/*- Settings for a terminal session -*/
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Put between the output of commands
+    pub line_break: String,
+
+    /// How many inputs to remember in the history
+    pub history_size: usize,
+
+    /// How many commands one input may run in total
+    pub max_steps: usize,
+
+    /// How deep commands may be nested, through `exec`, `eval(...)` and such
+    pub max_depth: usize,
+
+    /// How many bytes of output one input may produce. Also
+    /// how big a single word or variable may get
+    pub max_output: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            line_break: String::from("<br />"),
+            history_size: 100,
+            max_steps: 100_000,
+            max_depth: 64,
+            max_output: 1_000_000,
+        }
+    }
+}
+
+/*- A user-created function: name, body and parameter names -*/
+pub type Function = (String, Node, Vec<String>);
+
+/// A terminal session. Each session has its own
+/// variables, functions and history
+#[wasm_bindgen]
+pub struct Terminal {
+    variables: HashMap<String, String>,
+    functions: HashMap<String, Function>,
+    history: Vec<String>,
+    config: Config,
+
+    /*- Status of the last command, available as $? -*/
+    status: i32,
+
+    /*- Output of the previous command in a pipeline -*/
+    stdin: Option<String>,
+
+    /*- Errors and events from 'eval(...)' substitutions, which get
+        added to the result of the command that contained them -*/
+    pending: CommandResult,
+
+    /*- Random numbers, logging and time -*/
+    platform: Box<dyn Platform>,
+
+    /*- How much of the budget the current input has used. When a limit
+        is hit, the reason is kept and nothing else gets to run -*/
+    steps: usize,
+    depth: usize,
+    stopped: Option<String>,
+}
+
+#[wasm_bindgen]
+impl Terminal {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Terminal {
+        Terminal::with_config(Config::default())
+    }
+
+    /// Run a line of input
+    pub fn run(&mut self, input:String) -> CommandResult {
+        /*- Remember the input -*/
+        self.history.push(input.clone());
+        if self.history.len() > self.config.history_size {
+            self.history.remove(0);
+        };
+
+        self.steps = 0;
+        self.depth = 0;
+        self.stopped = None;
+
+        let mut result = match parser::parse(&input) {
+            Ok(node) => self.run_node(&node),
+            Err(error) => {
+                self.status = result::SYNTAX_ERROR;
+                CommandResult::err(error).with_status(result::SYNTAX_ERROR)
+            }
+        };
+
+        /*- Say why we stopped early -*/
+        if let Some(error) = self.stopped.take() {
+            result.append(CommandResult::err(error), &self.config.line_break);
+            self.status = result.status();
+        };
+
+        /*- '\n' escapes become whatever a line break is here -*/
+        result.replace_line_breaks(&self.config.line_break);
+        result
+    }
+
+    /// Forget all variables, functions and history
+    pub fn reset(&mut self) {
+        self.variables.clear();
+        self.functions.clear();
+        self.history.clear();
+        self.status = result::SUCCESS;
+    }
+
+    /// Replaces things like `<variable>` and `:random 1-10:`
+    /// with their values
+    pub fn replace_info(&self, mut input:String) -> String {
+
+        /*- $? is the status of the last command -*/
+        input = input.replace("$?", &self.status.to_string());
+
+        /*- Replace all variables, names that aren't set are left alone -*/
+        let input = VARIABLE.replace_all(&input, |caps: &Captures| {
+            match self.variables.get(&caps[1]) {
+                Some(value) => value.to_string(),
+                None => caps[0].to_string()
+            }
+        }).to_string();
+
+        /*- Make the random replacement -*/
+        let input = RANDOM.replace_all(&input, |caps: &Captures| {
+            let min = caps[1].parse::<i32>().unwrap_or(0);
+            let max = caps[2].parse::<i32>().unwrap_or(0);
+            let random = (self.random() * (max - min)as f64 + min as f64) as i32;
+            random.to_string()
+        }).to_string();
+
+        /*- Return -*/
+        input
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Terminal {
+    pub fn with_config(config:Config) -> Terminal {
+        Terminal::with_platform(config, platform::default_platform())
+    }
+
+    /// A terminal that gets random numbers, logging and time from `platform`
+    pub fn with_platform(config:Config, platform:Box<dyn Platform>) -> Terminal {
+        Terminal {
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            history: Vec::new(),
+            config,
+            status: result::SUCCESS,
+            stdin: None,
+            pending: CommandResult::default(),
+            platform,
+            steps: 0,
+            depth: 0,
+            stopped: None,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn platform(&self) -> &dyn Platform {
+        self.platform.as_ref()
+    }
+
+    /// A random number in `0..1`
+    pub(crate) fn random(&self) -> f64 {
+        self.platform.random()
+    }
+
+    /*- Run a parsed command tree -*/
+    pub(crate) fn run_node(&mut self, node:&Node) -> CommandResult {
+        if self.stopped() { return CommandResult::default().with_status(result::FAILURE) };
+
+        match node {
+            /*- A line can contain '&&', '||' and ';' which will make users
+                be able to execute multiple commands in one line -*/
+            Node::Sequence(commands) => {
+                let mut result = CommandResult::default();
+                for (operator, command) in commands {
+
+                    /*- Skipped commands keep the status of the one before -*/
+                    let run = match operator {
+                        Operator::And => self.status == result::SUCCESS,
+                        Operator::Or => self.status != result::SUCCESS,
+                        Operator::Then => true,
+                    };
+                    if !run { continue; };
+
+                    let command_result = self.run_node(command);
+                    self.append(&mut result, command_result);
+                };
+
+                /*- A sequence where nothing ran keeps the status it started with -*/
+                result.with_status(self.status)
+            },
+            /*- 'a | b' runs b with the output of a as its stdin -*/
+            Node::Pipeline(commands) => {
+                let mut result = CommandResult::default();
+                let mut stdin:Option<String> = None;
+
+                for command in commands {
+                    let mut command_result = self.run_with_stdin(command, stdin.take());
+                    stdin = Some(command_result.take_output());
+                    self.append(&mut result, command_result);
+                };
+
+                /*- Only the last command gets to print -*/
+                result.set_output(stdin.unwrap_or_default());
+                result
+            },
+            Node::Command(items) => {
+                /*- Get the command name -*/
+                let command_name = match items.first() {
+                    Some(name) => self.expand(name),
+                    None => return CommandResult::default()
+                };
+
+                /*- Every command uses up one step of the budget -*/
+                if !self.step() {
+                    return CommandResult::default().with_status(result::FAILURE);
+                };
+                if self.depth >= self.config.max_depth {
+                    self.stop(format!("Depth limit of {} nested commands reached, stopping.", self.config.max_depth));
+                    return CommandResult::default().with_status(result::FAILURE);
+                };
+
+                /*- Find the command and call it -*/
+                self.depth += 1;
+                let command_result = match COMMANDS.iter().find(|(command, _, _)| command == &command_name) {
+                    Some((_, caller, _)) => caller(self, &items[1..]),
+                    None => CommandResult::err(format!("Command not found: '{command_name}'")).with_status(result::NOT_FOUND)
+                };
+                self.depth -= 1;
+
+                /*- Errors from substitutions go before the ones of the command itself -*/
+                let mut result = std::mem::take(&mut self.pending);
+                self.append(&mut result, command_result);
+
+                self.status = result.status();
+                result
+            },
+            Node::Block(inner) => self.run_node(inner),
+            _ => {
+                let output = self.expand(node);
+                CommandResult::ok(output)
+            }
+        }
+    }
+
+    /// Add `other` after `result`, the way a sequence of commands
+    /// would. Stops everything if the output gets too big
+    pub(crate) fn append(&mut self, result:&mut CommandResult, other:CommandResult) {
+        result.append(other, &self.config.line_break);
+
+        let max_output = self.config.max_output;
+        if result.output_str().len() > max_output {
+            result.truncate_output(max_output);
+            self.stop(format!("Output limit of {max_output} bytes reached, stopping."));
+        };
+    }
+
+    /// Use up one step of the budget. False if the
+    /// step limit was hit and nothing more should run
+    pub(crate) fn step(&mut self) -> bool {
+        self.steps += 1;
+        if self.steps > self.config.max_steps {
+            self.stop(format!("Step limit of {} reached, stopping.", self.config.max_steps));
+        };
+        !self.stopped()
+    }
+
+    /// If a limit was hit and nothing more should run
+    pub(crate) fn stopped(&self) -> bool {
+        self.stopped.is_some()
+    }
+
+    /*- If a word or variable of 'size' bytes is small enough. The
+        output limit is used, since that's where it would end up -*/
+    fn fits(&mut self, size:usize) -> bool {
+        let max_output = self.config.max_output;
+        if size > max_output {
+            self.stop(format!("Size limit of {max_output} bytes for a word or variable reached, stopping."));
+        };
+        size <= max_output
+    }
+
+    /*- Only the first limit that was hit gets reported -*/
+    fn stop(&mut self, error:String) {
+        self.stopped.get_or_insert(error);
+    }
+
+    /*- Run a command with stdin, which is only
+        available to that command, not the ones after -*/
+    fn run_with_stdin(&mut self, node:&Node, stdin:Option<String>) -> CommandResult {
+        let outer = std::mem::replace(&mut self.stdin, stdin);
+        let result = self.run_node(node);
+        self.stdin = outer;
+        result
+    }
+
+    /// Take what was piped into the current command
+    pub(crate) fn take_stdin(&mut self) -> Option<String> {
+        self.stdin.take()
+    }
+
+    /*- Get the string value of an argument -*/
+    pub(crate) fn expand(&mut self, node:&Node) -> String {
+        match node {
+            Node::Literal(text) => {
+                /*- Something like <a><a> doubles every time it's
+                    stored again, so the size is checked up front -*/
+                let size = text.len() + VARIABLE.captures_iter(text)
+                    .filter_map(|caps| self.variables.get(&caps[1]))
+                    .map(String::len)
+                    .sum::<usize>();
+                if !self.fits(size) { return String::new() };
+
+                self.replace_info(text.to_string())
+            },
+            Node::Verbatim(text) => text.to_string(),
+            Node::Word(parts) => {
+                let mut word = String::new();
+                for part in parts {
+                    let part = self.expand(part);
+                    if !self.fits(word.len() + part.len()) { return String::new() };
+                    word.push_str(&part);
+                };
+                word
+            },
+
+            /*- 'eval(some_command)' and blocks are replaced with the output of the command -*/
+            Node::Substitution(inner) | Node::Block(inner) => self.substitute(inner),
+            Node::Call(name, arguments) => {
+                let arguments = self.expand_all(arguments);
+                self.call(name, &arguments)
+            },
+            Node::Sequence(_) | Node::Pipeline(_) | Node::Command(_) => self.substitute(node),
+        }
+    }
+
+    /*- Run a node for its output. Errors and events are kept
+        for the result of the command that is being expanded. What
+        was piped in is for that command, not for its arguments -*/
+    fn substitute(&mut self, node:&Node) -> String {
+        let mut result = self.run_with_stdin(node, None);
+        let output = result.take_output();
+        let mut pending = std::mem::take(&mut self.pending);
+        self.append(&mut pending, result);
+        self.pending = pending;
+        output
+    }
+
+    /*- Things like 'var(name)' and 'replace(string,replace,with)' -*/
+    fn call(&mut self, name:&str, arguments:&[String]) -> String {
+        match (name, arguments) {
+            /*- Typing 'var(variable_name)' will replace it with the value -*/
+            ("var", [variable_name]) => self.variable(variable_name),
+
+            /*- Typing 'replace(string,replace,with)' will replace all 'replace' with 'with' -*/
+            ("replace", [string, replace, with]) => {
+                let size = string.len() + string.matches(replace.as_str()).count() * with.len();
+                if !self.fits(size) { return String::new() };
+                string.replace(replace.as_str(), with)
+            },
+            _ => {
+                let error = CommandResult::err(format!("Invalid {name}(...) call with {} argument(s)!", arguments.len()));
+                self.pending.append(error, &self.config.line_break);
+                String::new()
+            }
+        }
+    }
+
+    pub(crate) fn expand_all(&mut self, nodes:&[Node]) -> Vec<String> {
+        nodes.iter().map(|e| self.expand(e)).collect()
+    }
+
+    /*- Variables -*/
+    pub(crate) fn variable(&self, variable_name:&str) -> String {
+        match self.variables.get(variable_name) {
+            Some(value) => value.to_string(),
+
+            /*- Return else -*/
+            None => String::from("null")
+        }
+    }
+
+    pub(crate) fn set_variable(&mut self, name:String, value:String) {
+        /*- A value cut short by a limit isn't stored either -*/
+        if self.stopped() || !self.fits(value.len()) { return };
+        self.variables.insert(name, value);
+    }
+
+    pub(crate) fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+
+    /*- Functions -*/
+    pub(crate) fn function(&self, name:&str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub(crate) fn set_function(&mut self, function:Function) {
+        self.functions.insert(function.0.clone(), function);
+    }
+
+    pub(crate) fn functions(&self) -> &HashMap<String, Function> {
+        &self.functions
+    }
+}
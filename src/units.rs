@@ -0,0 +1,150 @@
+/*- Unit table used by the 'convert' command -*/
+
+/// What a unit measures. Only units of the same dimension can be converted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Temperature,
+    Time,
+    Data,
+    Speed,
+}
+
+impl Dimension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Mass => "mass",
+            Dimension::Volume => "volume",
+            Dimension::Temperature => "temperature",
+            Dimension::Time => "time",
+            Dimension::Data => "data size",
+            Dimension::Speed => "speed",
+        }
+    }
+}
+
+/*- Names of the unit, what it measures, and how to get to the base
+    unit of the dimension: base = value * scale + offset. The base units
+    are meters, kilograms, liters, kelvin, seconds, bytes and meters
+    per second -*/
+type Unit = (&'static [&'static str], Dimension, f64, f64);
+
+const UNITS:&[Unit] = &[
+    /*- Length -*/
+    (&["m", "meter", "meters", "metre", "metres"], Dimension::Length, 1.0, 0.0),
+    (&["km", "kilometer", "kilometers", "kilometre", "kilometres"], Dimension::Length, 1000.0, 0.0),
+    (&["cm", "centimeter", "centimeters"], Dimension::Length, 0.01, 0.0),
+    (&["mm", "millimeter", "millimeters"], Dimension::Length, 0.001, 0.0),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344, 0.0),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144, 0.0),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048, 0.0),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254, 0.0),
+    (&["nmi", "nautical_mile", "nautical_miles"], Dimension::Length, 1852.0, 0.0),
+
+    /*- Mass -*/
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.0, 0.0),
+    (&["g", "gram", "grams"], Dimension::Mass, 0.001, 0.0),
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 0.000001, 0.0),
+    (&["t", "tonne", "tonnes"], Dimension::Mass, 1000.0, 0.0),
+    (&["lb", "lbs", "pound", "pounds"], Dimension::Mass, 0.45359237, 0.0),
+    (&["oz", "ounce", "ounces"], Dimension::Mass, 0.028349523125, 0.0),
+    (&["st", "stone", "stones"], Dimension::Mass, 6.35029318, 0.0),
+
+    /*- Volume, with US customary units -*/
+    (&["l", "L", "liter", "liters", "litre", "litres"], Dimension::Volume, 1.0, 0.0),
+    (&["ml", "mL", "milliliter", "milliliters"], Dimension::Volume, 0.001, 0.0),
+    (&["cl", "cL", "centiliter", "centiliters"], Dimension::Volume, 0.01, 0.0),
+    (&["dl", "dL", "deciliter", "deciliters"], Dimension::Volume, 0.1, 0.0),
+    (&["m3", "cubic_meter", "cubic_meters"], Dimension::Volume, 1000.0, 0.0),
+    (&["gal", "gallon", "gallons"], Dimension::Volume, 3.785411784, 0.0),
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946352946, 0.0),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473176473, 0.0),
+    (&["cup", "cups"], Dimension::Volume, 0.2365882365, 0.0),
+    (&["floz", "fluid_ounce", "fluid_ounces"], Dimension::Volume, 0.0295735295625, 0.0),
+    (&["tbsp", "tablespoon", "tablespoons"], Dimension::Volume, 0.01478676478125, 0.0),
+    (&["tsp", "teaspoon", "teaspoons"], Dimension::Volume, 0.00492892159375, 0.0),
+
+    /*- Temperature -*/
+    (&["K", "kelvin"], Dimension::Temperature, 1.0, 0.0),
+    (&["C", "°C", "celsius"], Dimension::Temperature, 1.0, 273.15),
+    (&["F", "°F", "fahrenheit"], Dimension::Temperature, 5.0 / 9.0, 273.15 - 32.0 * 5.0 / 9.0),
+
+    /*- Time -*/
+    (&["s", "sec", "second", "seconds"], Dimension::Time, 1.0, 0.0),
+    (&["ms", "millisecond", "milliseconds"], Dimension::Time, 0.001, 0.0),
+    (&["min", "minute", "minutes"], Dimension::Time, 60.0, 0.0),
+    (&["h", "hr", "hour", "hours"], Dimension::Time, 3600.0, 0.0),
+    (&["d", "day", "days"], Dimension::Time, 86400.0, 0.0),
+    (&["wk", "week", "weeks"], Dimension::Time, 604800.0, 0.0),
+    (&["yr", "year", "years"], Dimension::Time, 31557600.0, 0.0),
+
+    /*- Data sizes, decimal and binary prefixes. B is bytes and b is bits -*/
+    (&["B", "byte", "bytes"], Dimension::Data, 1.0, 0.0),
+    (&["bit", "bits"], Dimension::Data, 0.125, 0.0),
+    (&["kbit", "kb", "Kb", "kilobit", "kilobits"], Dimension::Data, 125.0, 0.0),
+    (&["Mbit", "Mb", "megabit", "megabits"], Dimension::Data, 125e3, 0.0),
+    (&["Gbit", "Gb", "gigabit", "gigabits"], Dimension::Data, 125e6, 0.0),
+    (&["Tbit", "Tb", "terabit", "terabits"], Dimension::Data, 125e9, 0.0),
+    (&["kB", "KB", "kilobyte", "kilobytes"], Dimension::Data, 1e3, 0.0),
+    (&["MB", "megabyte", "megabytes"], Dimension::Data, 1e6, 0.0),
+    (&["GB", "gigabyte", "gigabytes"], Dimension::Data, 1e9, 0.0),
+    (&["TB", "terabyte", "terabytes"], Dimension::Data, 1e12, 0.0),
+    (&["KiB", "kibibyte", "kibibytes"], Dimension::Data, 1024.0, 0.0),
+    (&["MiB", "mebibyte", "mebibytes"], Dimension::Data, 1048576.0, 0.0),
+    (&["GiB", "gibibyte", "gibibytes"], Dimension::Data, 1073741824.0, 0.0),
+    (&["TiB", "tebibyte", "tebibytes"], Dimension::Data, 1099511627776.0, 0.0),
+
+    /*- Speed -*/
+    (&["m/s", "mps"], Dimension::Speed, 1.0, 0.0),
+    (&["km/h", "kph", "kmh"], Dimension::Speed, 1.0 / 3.6, 0.0),
+    (&["mph", "mi/h"], Dimension::Speed, 0.44704, 0.0),
+    (&["ft/s", "fps"], Dimension::Speed, 0.3048, 0.0),
+    (&["kn", "knot", "knots"], Dimension::Speed, 1852.0 / 3600.0, 0.0),
+];
+
+/*- Exact names first, then any casing like 'Km' or 'MILES'. Data
+    sizes have to be exact, since 'Mb' is megabits and 'MB' megabytes -*/
+fn unit(name:&str) -> Result<&'static Unit, String> {
+    UNITS.iter().find(|(names, ..)| names.contains(&name))
+        .or_else(|| UNITS.iter().find(|(names, dimension, ..)| {
+            *dimension != Dimension::Data && names.iter().any(|e| e.eq_ignore_ascii_case(name))
+        }))
+        .ok_or_else(|| format!("Unknown unit '{name}'"))
+}
+
+/// Convert `value` from one unit to another
+pub fn convert(value:f64, from:&str, to:&str) -> Result<f64, String> {
+    let (from_unit, to_unit) = (unit(from)?, unit(to)?);
+    let (_, from_dimension, from_scale, from_offset) = from_unit;
+    let (_, to_dimension, to_scale, to_offset) = to_unit;
+
+    if from_dimension != to_dimension {
+        return Err(format!(
+            "Can't convert {from} ({}) to {to} ({})",
+            from_dimension.name(), to_dimension.name()
+        ));
+    };
+
+    let base = value * from_scale + from_offset;
+    Ok((base - to_offset) / to_scale)
+}
+
+/// Short names of all units, grouped by what they measure
+pub fn help(line_break:&str) -> String {
+    let mut lines:Vec<String> = Vec::new();
+    for (names, dimension, ..) in UNITS {
+        let line = format!("{}: ", dimension.name());
+        match lines.last_mut() {
+            Some(last) if last.starts_with(&line) => {
+                last.push_str(", ");
+                last.push_str(names[0]);
+            },
+            _ => lines.push(format!("{line}{}", names[0]))
+        };
+    };
+
+    lines.join(line_break)
+}
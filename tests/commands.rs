@@ -0,0 +1,602 @@
+/*- Imports -*/
+use wasm_terminal::{platform::Native, Config, Terminal};
+
+/*- A terminal with plain newlines and a fixed random seed -*/
+fn terminal() -> Terminal {
+    let config = Config { line_break: String::from("\n"), ..Default::default() };
+    Terminal::with_platform(config, Box::new(Native::with_seed(1)))
+}
+
+fn output(terminal:&mut Terminal, input:&str) -> String {
+    let result = terminal.run(input.to_string());
+    assert_eq!(result.error(), "", "{input}");
+    result.output()
+}
+
+#[test]
+fn calc() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc 2+3*4"), "14");
+    assert_eq!(output(&mut term, "return 7 | calc"), "7");
+}
+
+#[test]
+fn variables_and_functions() {
+    let mut term = terminal();
+    output(&mut term, "set name world");
+    assert_eq!(output(&mut term, "return hello <name>"), "hello world");
+
+    output(&mut term, "fn greet(who) return hi --who");
+    assert_eq!(output(&mut term, "exec greet(there)"), "hi there");
+}
+
+#[test]
+fn repeat() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "repeat 3 i {return #i}"), "0\n1\n2");
+}
+
+#[test]
+fn if_else() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "if (eval(calc 5*5) == 25) {return yes} else {return no}"), "yes");
+    assert_eq!(output(&mut term, "if (3 > 4) {return yes} else {return no}"), "no");
+}
+
+#[test]
+fn if_else_chains() {
+    let mut term = terminal();
+    output(&mut term, "set n 5");
+
+    let chain = "if (n > 10) {return big} else if (n > 7) {return medium} else if (n > 4) {return fourish} else {return small}";
+    assert_eq!(output(&mut term, chain), "fourish");
+    output(&mut term, "set n 1");
+    assert_eq!(output(&mut term, chain), "small");
+
+    /*- Without an else nothing runs -*/
+    assert_eq!(output(&mut term, "if (n > 10) {return big}"), "");
+    assert_eq!(output(&mut term, "if (n > 10) {return big} else if (n > 7) {return medium}"), "");
+    assert_eq!(output(&mut term, "if (n == 1) {return one}"), "one");
+
+    /*- Blocks can hold other ifs -*/
+    let nested = "if (n > 0) {if (n > 9) {return a} else if (n == 1) {if (true) {return nested}} else {return b}} else {return c}";
+    assert_eq!(output(&mut term, nested), "nested");
+
+    let result = term.run(String::from("if (n == 1) {return a} otherwise {return b}"));
+    assert_eq!(result.error(), "Expected |else {...}| or |else if (...) {...}| after the block");
+    let result = term.run(String::from("if (n == 1) {return a} else if {return b}"));
+    assert_eq!(result.error(), "Condition not found");
+}
+
+#[test]
+fn if_boolean_logic() {
+    let mut term = terminal();
+    output(&mut term, "set a 3");
+    output(&mut term, "set b 4");
+    output(&mut term, "set done false");
+
+    let check = |term:&mut Terminal, condition:&str| output(term, &format!("if ({condition}) {{return yes}} else {{return no}}"));
+    assert_eq!(check(&mut term, "(a > 1 && b < 5) || !done"), "yes");
+    assert_eq!(check(&mut term, "a > 5 || b > 5"), "no");
+    assert_eq!(check(&mut term, "!(a > 1) || done"), "no");
+
+    /*- && binds harder than || -*/
+    assert_eq!(check(&mut term, "true || false && false"), "yes");
+    assert_eq!(check(&mut term, "(true || false) && false"), "no");
+    assert_eq!(check(&mut term, "!!true"), "yes");
+
+    /*- Parentheses can also belong to a calculation -*/
+    assert_eq!(check(&mut term, "(a + 1) * 2 > 7"), "yes");
+    assert_eq!(check(&mut term, "a % 2 == 1 && <b> > 3"), "yes");
+    assert_eq!(check(&mut term, "a << 1 > 5"), "yes");
+    assert_eq!(check(&mut term, "hello == hello"), "yes");
+
+    /*- && and || skip what can't change the result -*/
+    output(&mut term, "set zero 0");
+    assert_eq!(check(&mut term, "zero != 0 && 10 / zero > 2"), "no");
+    assert_eq!(check(&mut term, "zero == 0 || 10 / zero > 2"), "yes");
+    assert_eq!(check(&mut term, "false && (word || 1 / 0)"), "no");
+    assert_eq!(check(&mut term, "true || word && 1 / 0 > 2"), "yes");
+
+    let result = term.run(String::from("if (1 / 0 > 2) {return yes} else {return no}"));
+    assert_eq!(result.error(), "Error parsing condition: Division by zero at column 3");
+    let result = term.run(String::from("if (word && true) {return yes} else {return no}"));
+    assert_eq!(result.error(), "Error parsing condition: 'word' isn't true or false at column 1");
+    let result = term.run(String::from("if (a > 1 &&) {return yes} else {return no}"));
+    assert_eq!(result.error(), "Error parsing condition: Expected a value at column 9");
+}
+
+#[test]
+fn if_comparisons() {
+    let mut term = terminal();
+    output(&mut term, "set name john");
+    output(&mut term, "set n 10");
+
+    let check = |term:&mut Terminal, condition:&str| output(term, &format!("if ({condition}) {{return yes}} else {{return no}}"));
+    assert_eq!(check(&mut term, "n >= 10 && n <= 10 && n != 9"), "yes");
+    assert_eq!(check(&mut term, "name != john"), "no");
+
+    /*- Numbers as numbers, text in alphabetical order -*/
+    assert_eq!(check(&mut term, "10 > 9"), "yes");
+    assert_eq!(check(&mut term, "10 == 10.0"), "yes");
+    assert_eq!(check(&mut term, "apple < banana && banana >= apple"), "yes");
+    assert_eq!(check(&mut term, "abc <= abd"), "yes");
+
+    assert_eq!(check(&mut term, "name contains oh && name startswith jo && name endswith hn"), "yes");
+    assert_eq!(check(&mut term, "name contains x || name startswith hn"), "no");
+    assert_eq!(check(&mut term, "name =~ ^j.h"), "yes");
+    assert_eq!(check(&mut term, "name =~ '^[a-z]{5}$'"), "no");
+
+    /*- Text operators look at what was written, not at constants like e and pi -*/
+    assert_eq!(check(&mut term, "hello contains e && hello =~ e"), "yes");
+    assert_eq!(check(&mut term, "recipe endswith e && pie startswith pi"), "yes");
+    assert_eq!(check(&mut term, "007 =~ ^00 && n startswith 1"), "yes");
+    assert_eq!(check(&mut term, "e == e && pi < pie"), "yes");
+
+    let result = term.run(String::from("if (name =~ '[') {return yes} else {return no}"));
+    assert_eq!(result.error(), "Error parsing condition: Invalid regex '[' at column 6");
+    let result = term.run(String::from("if (a==b==c) {return yes} else {return no}"));
+    assert_eq!(result.error(), "Error parsing condition: Comparisons can't be chained, join them with && at column 5");
+}
+
+#[test]
+fn status_and_operators() {
+    let mut term = terminal();
+    let result = term.run(String::from("nope || return fallback"));
+    assert_eq!(result.output(), "fallback");
+    assert_eq!(result.status(), 0);
+
+    let result = term.run(String::from("nope && return skipped"));
+    assert_eq!(result.output(), "");
+    assert_eq!(result.status(), 127);
+}
+
+#[test]
+fn pipes_go_to_the_command_not_its_arguments() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "return hi | return eval(return)x"), "x");
+    assert_eq!(output(&mut term, "return hi | replace eval(return h) j"), "ji");
+    assert_eq!(output(&mut term, "return 2 | calc eval(return 3 | calc) * 2"), "6");
+}
+
+#[test]
+fn escapes_follow_the_line_break() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, r"return a\nb"), "a\nb");
+
+    let mut html = Terminal::new();
+    assert_eq!(output(&mut html, r"return a\nb"), "a<br />b");
+}
+
+#[test]
+fn parentheses_outside_substitutions() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "return hi :)"), "hi :)");
+    assert_eq!(output(&mut term, "return ("), "(");
+    assert_eq!(output(&mut term, "return a(b c)d"), "a(b c)d");
+    assert_eq!(output(&mut term, "return ( hi && return there"), "( hi\nthere");
+
+    /*- Substitutions still have to be balanced -*/
+    assert_eq!(output(&mut term, "return eval(calc (1+2)*eval(return 3))"), "9");
+    assert_eq!(output(&mut term, "return eval(calc 2*(1+2))"), "6");
+    assert_eq!(output(&mut term, "return eval(calc sqrt(16))"), "4");
+    assert_eq!(output(&mut term, "return replace(a(b),a,c)"), "c(b)");
+    assert_eq!(output(&mut term, "if (eval(calc 2*(1+2)) == 6) {return yes} else {return no}"), "yes");
+    let result = term.run(String::from("return var(x"));
+    assert_eq!(result.error(), "Missing ')' for the 'var(' at column 8");
+}
+
+#[test]
+fn random_stays_in_range() {
+    let mut term = terminal();
+    for _ in 0..50 {
+        let number = output(&mut term, "random 1 10").parse::<i32>().unwrap();
+        assert!((1..10).contains(&number));
+    };
+}
+
+#[test]
+fn limits_stop_runaway_commands() {
+    let config = Config { max_steps: 500, ..Default::default() };
+    let mut term = Terminal::with_platform(config, Box::new(Native::with_seed(1)));
+    let result = term.run(String::from("repeat 100000000 i return x"));
+    assert_eq!(result.status(), 1);
+    assert!(result.error().contains("Step limit"), "{}", result.error());
+
+    /*- A body without commands still uses up steps -*/
+    let result = term.run(String::from("repeat 2000000000 i {}"));
+    assert_eq!(result.status(), 1);
+    assert!(result.error().contains("Step limit"), "{}", result.error());
+
+    output(&mut term, "fn forever() exec forever()");
+    let result = term.run(String::from("exec forever()"));
+    assert!(result.error().contains("Depth limit"), "{}", result.error());
+
+    let config = Config { max_output: 10, ..Default::default() };
+    let mut term = Terminal::with_platform(config, Box::new(Native::with_seed(1)));
+    let result = term.run(String::from("repeat 100 i return abc"));
+    assert!(result.output().len() <= 10);
+    assert!(result.error().contains("Output limit"), "{}", result.error());
+
+    /*- The next input gets a fresh budget -*/
+    assert_eq!(output(&mut term, "return ok"), "ok");
+
+    /*- Words and variables can't get bigger than the output either -*/
+    let config = Config { max_output: 1000, ..Default::default() };
+    let mut term = Terminal::with_platform(config, Box::new(Native::with_seed(1)));
+    output(&mut term, "set a x");
+    let result = term.run(String::from("repeat 27 i set a <a><a>"));
+    assert!(result.error().contains("Size limit"), "{}", result.error());
+    assert_eq!(output(&mut term, "return <a>"), "x".repeat(512));
+
+    let result = term.run(String::from("return replace(<a>,x,xx)"));
+    assert!(result.error().contains("Size limit"), "{}", result.error());
+    let result = term.run(String::from("set b <a>\"<a>\""));
+    assert!(result.error().contains("Size limit"), "{}", result.error());
+    assert_eq!(output(&mut term, "return <b>"), "<b>");
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let mut term = terminal();
+    let deep = |open:&str, inner:&str, close:&str, depth:usize| format!("{}{inner}{}", open.repeat(depth), close.repeat(depth));
+
+    for input in [
+        format!("calc {}", deep("(", "1", ")", 20000)),
+        format!("calc {}1", "1-".repeat(20000)),
+        format!("calc {}1", "2^".repeat(20000)),
+        format!("return {}", deep("{", "", "}", 20000)),
+        format!("return {}", deep("eval(", "", ")", 20000)),
+        format!("return {}", deep("replace(", "a", ",a,b)", 20000)),
+        format!("if {} {{ return yes }}", deep("(", "true", ")", 20000)),
+        format!("if ({}true) {{ return yes }}", "!".repeat(20000)),
+    ] {
+        let result = term.run(input);
+        assert_ne!(result.status(), 0);
+        assert!(result.error().to_lowercase().contains("nested too deep"), "{}", result.error());
+    };
+
+    /*- Not quite as deep is fine -*/
+    assert_eq!(output(&mut term, &format!("calc {}", deep("(", "1", ")", 150))), "1");
+    assert_eq!(output(&mut term, &format!("calc {}1", "1+".repeat(150))), "151");
+    assert_eq!(output(&mut term, &format!("return {}", deep("eval(return ", "a", ")", 50))), "a");
+}
+
+#[test]
+fn variable_placeholders() {
+    let mut term = terminal();
+    output(&mut term, "set b 2");
+    output(&mut term, "set a 1");
+    assert_eq!(output(&mut term, "return <a><b> <missing>"), "12 <missing>");
+    assert_eq!(output(&mut term, "list var"), "a | b");
+}
+
+#[test]
+fn calc_precedence() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc 2*3*4"), "24");
+    assert_eq!(output(&mut term, "calc (1+2)*3"), "9");
+    assert_eq!(output(&mut term, "calc 10 - 4 - 3"), "3");
+    assert_eq!(output(&mut term, "calc 2 ! 3 ! 2"), "512");
+    assert_eq!(output(&mut term, "calc 1 + 2 * 3 ! 2 % 5"), "4");
+    assert_eq!(output(&mut term, "calc 8 / (3 - 1) / 2"), "2");
+
+    let result = term.run(String::from("calc '(1+2'"));
+    assert_eq!(result.error(), "Error parsing calculation: Missing ')' to close the '(' at column 1");
+}
+
+#[test]
+fn calc_unary_and_notation() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc 2 * -3"), "-6");
+    assert_eq!(output(&mut term, "calc +2 - -2"), "4");
+    assert_eq!(output(&mut term, "calc -2^2"), "-4");
+    assert_eq!(output(&mut term, "calc 2^-1"), "0.5");
+    assert_eq!(output(&mut term, "calc 2**3**2"), "512");
+    assert_eq!(output(&mut term, "calc 1.5e3 + 2E-1"), "1500.2");
+
+    let result = term.run(String::from("calc 1.2.3 + 1"));
+    assert_eq!(result.error(), "Error parsing calculation: Invalid number '1.2.3' at column 1");
+    let result = term.run(String::from("calc 1e + 1"));
+    assert_eq!(result.error(), "Error parsing calculation: Invalid number '1e' at column 1");
+}
+
+#[test]
+fn calc_functions_and_constants() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc sqrt(16) + abs(-2)"), "6");
+    assert_eq!(output(&mut term, "calc max(1, 7, 3) - min(4, 2)"), "5");
+    assert_eq!(output(&mut term, "calc hypot(3, 4)"), "5");
+    assert_eq!(output(&mut term, "calc floor(2.7) + ceil(2.2) + round(2.5)"), "8");
+    assert_eq!(output(&mut term, "calc round(cos(pi))"), "-1");
+    assert_eq!(output(&mut term, "calc log10(1000)"), "3");
+    assert_eq!(output(&mut term, "calc tau / pi"), "2");
+
+    let result = term.run(String::from("calc sqrt(1, 2)"));
+    assert_eq!(result.error(), "Error parsing calculation: sqrt(...) takes 1 argument(s), not 2 at column 1");
+    let result = term.run(String::from("calc sqrt(-1)"));
+    assert_eq!(result.error(), "Error parsing calculation: sqrt(...) is undefined for those arguments at column 1");
+
+    let help = output(&mut term, "help calc");
+    assert!(help.contains("hypot(x, y)") && help.contains("tau = "), "{help}");
+}
+
+#[test]
+fn calc_precision_and_big_integers() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc 16777217 + 0"), "16777217");
+    assert_eq!(output(&mut term, "calc 0.1 + 0.2"), "0.3");
+    assert_eq!(output(&mut term, "calc 2^53"), "9007199254740992");
+    assert_eq!(output(&mut term, "calc 2^60"), "1152921504606846976");
+    assert_eq!(output(&mut term, "calc -2^62 + 0.5 * 2"), "-4611686018427387904");
+    assert_eq!(output(&mut term, "calc 1e20"), "100000000000000000000");
+    assert_eq!(output(&mut term, "calc --int 2!100"), "1267650600228229401496703205376");
+    assert_eq!(output(&mut term, "calc --int 99999999999999999999 + 1"), "100000000000000000000");
+    assert_eq!(output(&mut term, "calc --int -7 % 3 + max(2, 10) / 5"), "1");
+
+    /*- Results are limited to a million bits -*/
+    assert_eq!(output(&mut term, "calc --int 2^999999 % 10"), "8");
+    let result = term.run(String::from("calc --int 2^1000000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 2");
+    let result = term.run(String::from("calc --int 3^500000 * 3^500000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 10");
+    let result = term.run(String::from("calc --int 2^999999 + 2^999999"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 10");
+    assert_eq!(output(&mut term, "return 12345678901234567890*2 | calc --int"), "24691357802469135780");
+
+    let result = term.run(String::from("calc --int 7 / 2"));
+    assert_eq!(result.error(), "Error parsing calculation: 7 / 2 isn't a whole number at column 3");
+    let result = term.run(String::from("calc --int 1.5 + 1"));
+    assert_eq!(result.error(), "Error parsing calculation: '1.5' isn't a whole number, which --int needs at column 1");
+}
+
+#[test]
+fn calc_variables() {
+    let mut term = terminal();
+    output(&mut term, "set price 2.5");
+    output(&mut term, "set qty -4");
+    assert_eq!(output(&mut term, "calc total = price * qty"), "-10");
+    assert_eq!(output(&mut term, "return <total>"), "-10");
+    assert_eq!(output(&mut term, "calc ans * 2"), "-20");
+    assert_eq!(output(&mut term, "calc ans + total"), "-30");
+    assert_eq!(output(&mut term, "calc --int total * 3"), "-30");
+
+    let result = term.run(String::from("calc missing + 1"));
+    assert_eq!(result.error(), "Error parsing calculation: Unknown name 'missing' at column 1");
+    output(&mut term, "set word hello");
+    let result = term.run(String::from("calc word + 1"));
+    assert_eq!(result.error(), "Error parsing calculation: Variable 'word' isn't a number: 'hello' at column 1");
+    let result = term.run(String::from("calc 1 = 2"));
+    assert_eq!(result.error(), "Error parsing calculation: Unexpected '=' at column 3");
+}
+
+#[test]
+fn calc_bases_and_bitwise() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc 0xff + 0b101 + 0o17"), "275");
+    assert_eq!(output(&mut term, "calc 0xf0 & 0x3c"), "48");
+    assert_eq!(output(&mut term, "calc '0xf0 | 0x0f'"), "255");
+    assert_eq!(output(&mut term, "calc (1 | 2) xor 7"), "4");
+    assert_eq!(output(&mut term, "calc 1 << 4 + 1"), "32");
+    assert_eq!(output(&mut term, "calc ~5 >> 1"), "-3");
+    assert_eq!(output(&mut term, "calc --int 1 << 100 >> 99"), "2");
+
+    /*- Whole numbers stay exact without --int -*/
+    assert_eq!(output(&mut term, "calc 0x7ffffffffffffffe & 0x7ffffffffffffffe"), "9223372036854775806");
+    assert_eq!(output(&mut term, "calc '0x20000000000001 | 0'"), "9007199254740993");
+    assert_eq!(output(&mut term, "calc 2^63 & 1"), "0");
+    assert_eq!(output(&mut term, "calc 10^30 + 1"), "1000000000000000000000000000001");
+    assert_eq!(output(&mut term, "calc 7 / 2 + 0xf"), "18.5");
+    let result = term.run(String::from("calc 2.0^63 & 1"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 8");
+
+    let result = term.run(String::from("calc 1.5 & 1"));
+    assert_eq!(result.error(), "Error parsing calculation: '&' needs whole numbers, not 1.5 at column 5");
+    let result = term.run(String::from("calc 0xfg"));
+    assert_eq!(result.error(), "Error parsing calculation: Invalid number '0xfg' at column 1");
+
+    assert_eq!(output(&mut term, "base 255 16"), "ff");
+    assert_eq!(output(&mut term, "base 0xff 2"), "11111111");
+    assert_eq!(output(&mut term, "base 2^64 16"), "10000000000000000");
+    let result = term.run(String::from("base 10 1"));
+    assert_eq!(result.error(), "Base has to be a number from 2 to 36!");
+}
+
+#[test]
+fn convert_units() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "convert 5 km to mi"), "3.10685596118667 mi");
+    assert_eq!(output(&mut term, "convert 100 C to F"), "212 F");
+    assert_eq!(output(&mut term, "convert -40 fahrenheit to celsius"), "-40 celsius");
+    assert_eq!(output(&mut term, "convert 2^10 KiB to MB"), "1.048576 MB");
+    assert_eq!(output(&mut term, "convert 1 gal to L"), "3.785411784 L");
+    assert_eq!(output(&mut term, "convert 90 min to h"), "1.5 h");
+    assert_eq!(output(&mut term, "convert 36 km/h to m/s"), "10 m/s");
+    assert_eq!(output(&mut term, "convert 16 Oz to LB"), "1 LB");
+
+    /*- Casing matters for data sizes, b is bits and B is bytes -*/
+    assert_eq!(output(&mut term, "convert 1 Kb to B"), "125 B");
+    assert_eq!(output(&mut term, "convert 8 Mb to MB"), "1 MB");
+    assert_eq!(output(&mut term, "convert 1 GB to Gbit"), "8 Gbit");
+    let result = term.run(String::from("convert 1 mb to B"));
+    assert_eq!(result.error(), "Unknown unit 'mb'");
+
+    let result = term.run(String::from("convert 5 km to kg"));
+    assert_eq!(result.error(), "Can't convert km (length) to kg (mass)");
+    let result = term.run(String::from("convert 5 parsecs to km"));
+    assert_eq!(result.error(), "Unknown unit 'parsecs'");
+    let result = term.run(String::from("convert 5 km"));
+    assert_eq!(result.error(), "Units not specified! Type |help convert| for further info.");
+}
+
+#[test]
+fn calc_fractions() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "calc --frac 1/3 + 1/6"), "1/2");
+    assert_eq!(output(&mut term, "calc --frac 1/3*3"), "1");
+    assert_eq!(output(&mut term, "calc --frac 0.1 + 0.2 - 3e-1"), "0");
+    assert_eq!(output(&mut term, "calc --frac (2/3)^-2"), "9/4");
+    assert_eq!(output(&mut term, "calc --frac --dec 1/8 + 1/4"), "0.375");
+
+    /*- Fractions stored in variables can be used in every mode -*/
+    assert_eq!(output(&mut term, "calc --frac third = 1/3"), "1/3");
+    assert_eq!(output(&mut term, "calc --frac third * 3"), "1");
+    assert_eq!(output(&mut term, "calc third * 6"), "2");
+
+    let result = term.run(String::from("calc --frac sqrt(2)"));
+    assert_eq!(result.error(), "Error parsing calculation: sqrt(...) can't be used with --frac at column 1");
+    let result = term.run(String::from("calc --frac 2 ^ (1/2)"));
+    assert_eq!(result.error(), "Error parsing calculation: 2 ^ (1/2) isn't a fraction at column 3");
+
+    /*- Tops and bottoms are limited to 20000 bits -*/
+    assert_eq!(output(&mut term, "calc --frac (1/3)^8000 * 3^8000"), "1");
+    let result = term.run(String::from("calc --frac (1/3)^8000 * (1/3)^8000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 12");
+    let result = term.run(String::from("calc --frac (1/3)^600000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 6");
+}
+
+#[test]
+fn statistics() {
+    let mut term = terminal();
+    assert_eq!(output(&mut term, "sum 1, 2, 3.5"), "6.5");
+    assert_eq!(output(&mut term, "mean 1 2 3 4"), "2.5");
+    assert_eq!(output(&mut term, "median 5 1 3 2"), "2.5");
+    assert_eq!(output(&mut term, "mode 1 2 2 3 3"), "2, 3");
+    assert_eq!(output(&mut term, "variance 1 2 3 4"), "1.25");
+    assert_eq!(output(&mut term, "stddev --sample 2 4 4 4 5 5 7 9"), "2.1380899352994");
+    assert_eq!(output(&mut term, "percentile 90 1 2 3 4 5"), "4.6");
+    assert_eq!(output(&mut term, "minmax 4,-1,7"), "min      -1\nmax      7");
+    assert_eq!(output(&mut term, "repeat 4 i return #i | mean"), "1.5");
+    assert_eq!(output(&mut term, "stats 1, 2, 2, 5"), [
+        "count    4", "sum      10", "mean     2.5", "median   2", "mode     2",
+        "min      1", "max      5", "range    4", "variance 2.25", "stddev   1.5",
+    ].join("\n"));
+
+    let result = term.run(String::from("mean 1 two 3"));
+    assert_eq!(result.error(), "'two' isn't a number");
+    let result = term.run(String::from("mean"));
+    assert_eq!(result.error(), "Numbers not specified! Type |help mean| for further info.");
+    let result = term.run(String::from("percentile 120 1 2"));
+    assert_eq!(result.error(), "Percentile has to be from 0 to 100, not 120");
+}
+
+#[test]
+fn calc_errors_point_to_the_column() {
+    let mut term = terminal();
+
+    let result = term.run(String::from("calc 1 + 2 / 0"));
+    assert_eq!(result.error(), "Error parsing calculation: Division by zero at column 7");
+    let result = term.run(String::from("calc 7 % (3 - 3)"));
+    assert_eq!(result.error(), "Error parsing calculation: Division by zero at column 3");
+    let result = term.run(String::from("calc --int 1 / 0"));
+    assert_eq!(result.error(), "Error parsing calculation: Division by zero at column 3");
+    let result = term.run(String::from("calc --frac 1 / (2 - 2)"));
+    assert_eq!(result.error(), "Error parsing calculation: Division by zero at column 3");
+
+    /*- Unbalanced parentheses -*/
+    let result = term.run(String::from("calc '2 * (1 + (2 * 3)'"));
+    assert_eq!(result.error(), "Error parsing calculation: Missing ')' to close the '(' at column 5");
+    let result = term.run(String::from("calc '1 + 2)'"));
+    assert_eq!(result.error(), "Error parsing calculation: Unexpected ')' at column 6");
+
+    /*- Unknown tokens and leftovers -*/
+    let result = term.run(String::from("calc 2 # 3"));
+    assert_eq!(result.error(), "Error parsing calculation: Unexpected '#' at column 3");
+    let result = term.run(String::from("calc 1 2"));
+    assert_eq!(result.error(), "Error parsing calculation: Expected an operator at column 3");
+    let result = term.run(String::from("calc 1 +"));
+    assert_eq!(result.error(), "Error parsing calculation: Expected a number at column 4");
+
+    /*- Overflow -*/
+    let result = term.run(String::from("calc 10 ^ 400"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 4");
+    let result = term.run(String::from("calc 2^1023 + 2^1023"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 8");
+    let result = term.run(String::from("calc 3^500000 * 3^500000 * 3^500000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 2");
+    let result = term.run(String::from("calc 1e999"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 1");
+    let result = term.run(String::from("calc 2 + 1e999"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 5");
+
+    /*- Too big for a float isn't too big for exact numbers -*/
+    assert_eq!(output(&mut term, "calc 0x10000000000000000"), "18446744073709551616");
+    assert_eq!(output(&mut term, "calc --frac 1e999 / 1e1000"), "1/10");
+    let result = term.run(String::from("calc --int 2 << 2000000"));
+    assert_eq!(result.error(), "Error parsing calculation: Number is too big at column 3");
+
+    /*- Nothing is stored when it fails -*/
+    let result = term.run(String::from("calc total = 1 / 0"));
+    assert_eq!(result.error(), "Error parsing calculation: Division by zero at column 11");
+    assert_eq!(term.run(String::from("calc total")).error(), "Error parsing calculation: Unknown name 'total' at column 1");
+}
+
+#[test]
+fn plot() {
+    let mut term = terminal();
+
+    let graph = [
+        " 2 |     |  **",
+        "   |     |**",
+        " 0 |----**----",
+        "   |  ** |",
+        "-2 |**   |",
+        "   +----------",
+        "    -2   0   2",
+    ];
+    assert_eq!(output(&mut term, "plot x -2 2 --width 10 --height 5"), graph.join("\n"));
+
+    /*- The range defaults to -10 to 10, and can use variables -*/
+    let result = output(&mut term, "plot x^2");
+    assert_eq!(result.lines().count(), 22);
+    assert!(result.trim_start().starts_with("100 |*"));
+    output(&mut term, "set n 3");
+    assert!(output(&mut term, "plot n*x 0 1 --width 10 --height 5").starts_with("3 ||       **"));
+
+    /*- Points that can't be calculated are left out -*/
+    assert!(output(&mut term, "plot 1/x -1 1 --width 21 --height 7").contains("*****-----+------****"));
+    let result = term.run(String::from("plot sqrt(x) -4 -1"));
+    assert_eq!(result.error(), "Error parsing calculation: sqrt(...) is undefined for those arguments at column 1");
+
+    let result = term.run(String::from("plot y"));
+    assert_eq!(result.error(), "Error parsing calculation: Unknown name 'y' at column 1");
+    let result = term.run(String::from("plot x --width 5"));
+    assert_eq!(result.error(), "Width has to be a number from 10 to 200!");
+    let result = term.run(String::from("plot x 1 -1"));
+    assert_eq!(result.error(), "The start of the range has to be below the end!");
+    let result = term.run(String::from("plot"));
+    assert_eq!(result.error(), "Calculation not specified! Type |help plot| for further info.");
+}
+
+#[test]
+fn while_and_until() {
+    let mut term = terminal();
+
+    /*- Collatz sequence of 6 -*/
+    output(&mut term, "set n 6");
+    output(&mut term, "set steps 0");
+    output(&mut term, "while (n != 1) {if (n % 2 == 0) {calc n = n / 2} else {calc n = 3 * n + 1}; calc steps = steps + 1}");
+    assert_eq!(output(&mut term, "calc steps"), "8");
+
+    output(&mut term, "set i 0");
+    assert_eq!(output(&mut term, "ol until (i >= 3) {calc i = i + 1}"), "1 2 3");
+
+    /*- The condition is checked before the first run -*/
+    assert_eq!(output(&mut term, "while (false) {return never}"), "");
+    assert_eq!(output(&mut term, "until (true) {return never}"), "");
+
+    let result = term.run(String::from("while (word) {return a}"));
+    assert_eq!(result.error(), "Error parsing condition: 'word' isn't true or false at column 1");
+    let result = term.run(String::from("until {return a}"));
+    assert_eq!(result.error(), "Condition not found! Type |help until| for further info.");
+
+    /*- Loops that never end run into the step limit -*/
+    let config = Config { max_steps: 500, ..Default::default() };
+    let mut term = Terminal::with_platform(config, Box::new(Native::with_seed(1)));
+    let result = term.run(String::from("while (true) {set x 1}"));
+    assert_eq!(result.status(), 1);
+    assert!(result.error().contains("Step limit"), "{}", result.error());
+    let result = term.run(String::from("until (false) {}"));
+    assert!(result.error().contains("Step limit"), "{}", result.error());
+}